@@ -1,3 +1,10 @@
 pub mod primitives;
 pub mod beauty_math;
-pub mod colors;
\ No newline at end of file
+pub mod colors;
+pub mod transform;
+pub mod canvas;
+pub mod framebuffer;
+pub mod clip;
+pub mod text;
+pub mod image;
+pub mod dither;
\ No newline at end of file
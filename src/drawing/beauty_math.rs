@@ -1,7 +1,27 @@
+pub mod parallel;
+pub mod view;
+pub mod coloring;
+pub mod double_double;
+pub mod perturbation;
+pub mod zoom;
+pub mod escape_time;
+pub mod progressive;
+pub mod simd;
+pub mod buddhabrot;
+pub mod flame;
+pub mod lsystem;
+
 use std::f64::consts::PI;
 use winapi::shared::windef::HDC;
 
 use super::primitives::{draw_line, create_solid_pen, close_draw_lines};
+use super::canvas::{Canvas, Surface};
+use super::colors::{RGB, interpolate_colors};
+use super::colors::harmony::Theme;
+use super::colors::colormap::Colormap;
+use view::FractalView;
+use coloring::FractalColors;
+use escape_time::{EscapeTime, Mandelbrot};
 
 static mut ORIG_X: f64 = 0.0;
 static mut ORIG_Y: f64 = 0.0;
@@ -106,19 +126,225 @@ pub fn draw_galaxy_step_inc(hdc: HDC, galaxy: &mut Galaxy) {
 
 //---------------------------------------------------------------------------------------------------------------------------
 
+#[derive(Clone, Debug)]
+pub enum SpiralColor {
+    /// COLORREF
+    Solid(u32),
+    /// Colors from the centre to the end of the spiral
+    Gradient(Vec<RGB<u8>>),
+}
+
+impl SpiralColor {
+    /// Gradient from the primary color through the accents
+    pub fn from_theme(theme: &Theme) -> SpiralColor {
+        SpiralColor::Gradient(theme.colors())
+    }
+}
+
+/// Sizes are relative to the smaller side of the surface, so the spiral looks the same at any resolution.
+///
+/// Defaults reproduce the classic spiral at 1920x1080.
+#[derive(Clone, Debug)]
+pub struct SpiralParams {
+    /// Normalized position of the centre, (0.5, 0.5) is the middle of the surface
+    pub center: (f64, f64),
+    /// Number of dots
+    pub count: usize,
+    /// Radius of the dot `i` is `angle(i) ^ growth_exponent`
+    pub growth_exponent: f64,
+    /// Multiplier for the radius
+    pub radius_scale: f64,
+    /// Angle between first dots, in radians
+    pub angle_step: f64,
+    /// Every `step_period` dots the angle step becomes `angle_step / ((i / step_period) ^ step_curve + 1)`
+    pub step_period: usize,
+    pub step_curve: f64,
+    pub dot_radius: f64,
+    pub color: SpiralColor,
+    /// Radians per second
+    pub rotation_speed: f64,
+}
+
+impl Default for SpiralParams {
+    fn default() -> Self {
+        SpiralParams {
+            center: (0.5, 0.5),
+            count: 1000,
+            growth_exponent: 0.8,
+            radius_scale: 10.0 / 1080.0,
+            angle_step: 0.05,
+            step_period: 500,
+            step_curve: 0.4,
+            dot_radius: 3.0 / 1080.0,
+            color: SpiralColor::Solid(0xFFFFFF),
+            rotation_speed: 0.0,
+        }
+    }
+}
+
+/// Animated spiral of dots
+#[derive(Clone, Debug, Default)]
+pub struct Spiral {
+    pub params: SpiralParams,
+    /// Current rotation, in radians
+    pub rotation: f64,
+}
+
+impl Spiral {
+    pub fn new(params: SpiralParams) -> Spiral {
+        Spiral { params, rotation: 0.0 }
+    }
+
+    /// `delta_time` in seconds
+    pub fn update(&mut self, delta_time: f64) {
+        self.rotation = (self.rotation + self.params.rotation_speed * delta_time) % (2.0 * PI);
+    }
+
+    /// Canvas local coordinates must be pixels of the surface (see [`Canvas::new()`])
+    pub fn draw<S: Surface>(&self, canvas: &mut Canvas<S>) {
+        self.params.draw(canvas, self.rotation);
+    }
+}
+
+impl SpiralParams {
+    /// Draw spiral rotated by `rotation` radians. Canvas local coordinates must be pixels of the surface
+    pub fn draw<S: Surface>(&self, canvas: &mut Canvas<S>, rotation: f64) {
+        let width = canvas.width() as f64;
+        let height = canvas.height() as f64;
+        let unit = width.min(height);
+
+        canvas.push();
+        canvas.translate(self.center.0 * width, self.center.1 * height);
+        canvas.rotate(rotation);
+        canvas.scale(unit, unit);
+
+        let mut angle = 0.0f64;
+        for i in 0..self.count {
+            // Compute radius based on angle
+            let radius = angle.powf(self.growth_exponent) * self.radius_scale;
+
+            // Convert polar coordinates to Cartesian coordinates
+            let point = (radius * angle.cos(), radius * angle.sin());
+            let color = match &self.color {
+                SpiralColor::Solid(color) => *color,
+                SpiralColor::Gradient(colors) => interpolate_colors(colors, i as f32 / self.count as f32),
+            };
+            canvas.fill_circle(point, self.dot_radius, color);
+
+            // Increment the angle for the next iteration
+            let c = ((i / self.step_period.max(1)) as f64).powf(self.step_curve) + 1.0;
+            angle += self.angle_step / c;
+        }
+
+        canvas.pop();
+    }
+}
+
+//---------------------------------------------------------------------------------------------------------------------------
+
 /// `pixels` must be initialized with <b>width * height</b> size
 pub fn calc_mandelbrot(width: usize, height: usize, max_iter: u32, pixels: &mut Vec<u32>) {
     for y in 0..height {
         for x in 0..width {
-            let cx = (x as f64 - width as f64 / 2.0) * 4.0 / width as f64;
-            let cy = (y as f64 - height as f64 / 2.0) * 4.0 / height as f64;
+            let (cx, cy) = mandelbrot_point(x, y, width, height);
+            pixels[y * width + x] = grey(mandelbrot(cx, cy, max_iter));
+        }
+    }
+}
+
+/// Same output as [`calc_mandelbrot()`], rendered on all hardware threads with SIMD
+pub fn calc_mandelbrot_parallel(width: usize, height: usize, max_iter: u32, pixels: &mut [u32]) {
+    mandelbrot_rows(width, height, max_iter, pixels, |x, y| mandelbrot_point(x, y, width, height), grey);
+}
 
-            let color_value = mandelbrot(cx, cy, max_iter) % 256;
-            let color = (color_value << 16) | (color_value << 8) | color_value;
+/// Like [`calc_mandelbrot_parallel()`], but iteration counts are colored with `colormap`. Points of the set are black
+pub fn calc_mandelbrot_colormap(width: usize, height: usize, max_iter: u32, colormap: Colormap, pixels: &mut [u32]) {
+    let lut = colormap.to_lut(max_iter as usize + 1);
+    let lut = lut.values();
+    mandelbrot_rows(width, height, max_iter, pixels, |x, y| mandelbrot_point(x, y, width, height), |i| {
+        if i == max_iter { 0 } else { lut[i as usize].to_pixel() }
+    });
+}
 
-            pixels[y * width + x] = color;
+/// Like [`calc_mandelbrot_colormap()`], but the visible area is set by `view` and keeps the aspect ratio
+pub fn calc_mandelbrot_view(
+    view: &FractalView,
+    width: usize,
+    height: usize,
+    max_iter: u32,
+    colormap: Colormap,
+    pixels: &mut [u32],
+) {
+    let lut = colormap.to_lut(max_iter as usize + 1);
+    let lut = lut.values();
+    let to_complex = view.to_complex(width, height);
+    mandelbrot_rows(width, height, max_iter, pixels, |x, y| to_complex.apply((x as f64 + 0.5, y as f64 + 0.5)), |i| {
+        if i == max_iter { 0 } else { lut[i as usize].to_pixel() }
+    });
+}
+
+/// Mandelbrot in `view` with smooth, distance estimation or orbit trap coloring, see [`FractalColors`]
+pub fn render_mandelbrot(
+    view: &FractalView,
+    width: usize,
+    height: usize,
+    max_iter: u32,
+    colors: &FractalColors,
+    pixels: &mut [u32],
+) {
+    render_fractal(&Mandelbrot, view, width, height, max_iter, colors, pixels);
+}
+
+/// Any [`EscapeTime`] fractal in `view`, e.g. `render_fractal(&Julia::at_angle(0.7885, time), ...)`
+pub fn render_fractal<F: EscapeTime + ?Sized>(
+    fractal: &F,
+    view: &FractalView,
+    width: usize,
+    height: usize,
+    max_iter: u32,
+    colors: &FractalColors,
+    pixels: &mut [u32],
+) {
+    let to_complex = view.to_complex(width, height);
+    let pixel_size = view.pixel_size(width, height);
+    let trap = colors.trap();
+    parallel::render_rows(width, height, pixels, |x, y| {
+        let point = to_complex.apply((x as f64 + 0.5, y as f64 + 0.5));
+        colors.pixel(&fractal.orbit(point, max_iter, trap), max_iter, pixel_size)
+    });
+}
+
+/// Iterate the bands on all hardware threads with [`simd::mandelbrot_points()`], `point(x, y)` is the complex
+/// number of the pixel and `color(iterations)` the resulting pixel
+fn mandelbrot_rows<P, C>(width: usize, height: usize, max_iter: u32, pixels: &mut [u32], point: P, color: C)
+where
+    P: Fn(usize, usize) -> (f64, f64) + Sync,
+    C: Fn(u32) -> u32 + Sync,
+{
+    parallel::render_band_slices(width, height, pixels, parallel::thread_count(), |top, band| {
+        let rows = band.len() / width;
+        let (cx, cy): (Vec<f64>, Vec<f64>) =
+            (top..top + rows).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| point(x, y)).unzip();
+        simd::mandelbrot_points(&cx, &cy, max_iter, band);
+        for value in band.iter_mut() {
+            *value = color(*value);
         }
-    }
+    });
+}
+
+/// Complex number of the pixel, the whole image covers `[-2, 2]` on both axes
+#[inline(always)]
+fn mandelbrot_point(x: usize, y: usize, width: usize, height: usize) -> (f64, f64) {
+    let cx = (x as f64 - width as f64 / 2.0) * 4.0 / width as f64;
+    let cy = (y as f64 - height as f64 / 2.0) * 4.0 / height as f64;
+    (cx, cy)
+}
+
+/// `(i % 256)` replicated to all channels
+#[inline(always)]
+fn grey(iterations: u32) -> u32 {
+    let color_value = iterations % 256;
+    (color_value << 16) | (color_value << 8) | color_value
 }
 
 #[inline(always)]
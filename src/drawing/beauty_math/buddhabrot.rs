@@ -0,0 +1,338 @@
+use std::f64::consts::FRAC_PI_2;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::super::colors::RGB;
+use super::super::framebuffer::Framebuffer;
+use super::super::transform::Transform;
+use super::mandelbrot;
+use super::parallel;
+use super::view::FractalView;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum BuddhabrotMode {
+    /// Orbits of points outside the set
+    #[default]
+    Buddhabrot,
+    /// Orbits of points that stay bounded, the inside of the set
+    AntiBuddhabrot,
+}
+
+/// Density histogram of orbits that escape (or stay) within `max_iter`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BuddhabrotChannel {
+    pub max_iter: u32,
+    /// Shorter orbits are skipped, they only add a blurry haze
+    pub min_iter: u32,
+    /// Color of the densest pixels
+    pub color: RGB<u8>,
+}
+
+impl BuddhabrotChannel {
+    pub const fn new(max_iter: u32, min_iter: u32, color: RGB<u8>) -> Self {
+        Self { max_iter, min_iter, color }
+    }
+}
+
+/// Buddhabrot scene, the image fills in over time as more random `c` are sampled.
+/// Orbits of the sampled points are accumulated into a density histogram per channel,
+/// which is tone mapped on [`Self::draw()`].
+///
+/// Example:
+/// ```
+/// let mut scene = Buddhabrot::nebulabrot(width, height).with_seed(7);
+/// // every frame
+/// scene.update(delta_time);
+/// scene.draw(&mut framebuffer);
+/// ```
+#[derive(Debug)]
+pub struct Buddhabrot {
+    /// Samples of `c` per second of [`Self::update()`]
+    pub samples_per_second: f64,
+    /// Brightness multiplier before the curve
+    pub exposure: f32,
+    /// Power applied to normalized densities, below 1 brings out faint orbits
+    pub gamma: f32,
+    width: usize,
+    height: usize,
+    mode: BuddhabrotMode,
+    view: FractalView,
+    channels: Vec<BuddhabrotChannel>,
+    /// `width * height` counters per channel, allocated on the first sample. 64 bits, a scene left running for days
+    /// would wrap 32 bit counters of the densest pixels
+    histogram: Vec<AtomicU64>,
+    /// One generator per thread
+    rngs: Vec<StdRng>,
+    samples: u64,
+    /// Fractional samples carried to the next update
+    pending: f64,
+}
+
+impl Buddhabrot {
+    /// Classic white Buddhabrot, sitting upright
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            samples_per_second: 200_000.0,
+            exposure: 1.0,
+            gamma: 0.5,
+            width,
+            height,
+            mode: BuddhabrotMode::Buddhabrot,
+            view: FractalView::new((-0.4, 0.0), 1.2).with_rotation(FRAC_PI_2),
+            channels: vec![BuddhabrotChannel::new(1000, 20, RGB::new(255, 255, 255))],
+            histogram: Vec::new(),
+            rngs: Vec::new(),
+            samples: 0,
+            pending: 0.0,
+        }
+        .with_seed(rand::thread_rng().gen())
+    }
+
+    /// Nebulabrot: red, green and blue channels of 5000, 500 and 50 iterations
+    pub fn nebulabrot(width: usize, height: usize) -> Self {
+        Self::new(width, height).with_channels(vec![
+            BuddhabrotChannel::new(5000, 20, RGB::new(255, 0, 0)),
+            BuddhabrotChannel::new(500, 20, RGB::new(0, 255, 0)),
+            BuddhabrotChannel::new(50, 20, RGB::new(0, 0, 255)),
+        ])
+    }
+
+    /// Anti-Buddhabrot with a single channel. Fewer samples per second, every sample runs the full `max_iter`
+    pub fn anti(width: usize, height: usize) -> Self {
+        let mut scene = Self::new(width, height)
+            .with_mode(BuddhabrotMode::AntiBuddhabrot)
+            .with_channels(vec![BuddhabrotChannel::new(500, 0, RGB::new(255, 255, 255))]);
+        scene.samples_per_second = 50_000.0;
+        scene
+    }
+
+    pub fn with_mode(mut self, mode: BuddhabrotMode) -> Self {
+        self.mode = mode;
+        self.clear();
+        self
+    }
+
+    pub fn with_view(mut self, view: FractalView) -> Self {
+        self.view = view;
+        self.clear();
+        self
+    }
+
+    pub fn with_channels(mut self, channels: Vec<BuddhabrotChannel>) -> Self {
+        self.channels = channels;
+        self.clear();
+        self
+    }
+
+    /// Same seed gives the same image after the same number of updates
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.rngs = (0..parallel::thread_count()).map(|_| StdRng::seed_from_u64(rng.gen())).collect();
+        self.clear();
+        self
+    }
+
+    pub fn mode(&self) -> BuddhabrotMode {
+        self.mode
+    }
+
+    pub fn view(&self) -> &FractalView {
+        &self.view
+    }
+
+    pub fn channels(&self) -> &[BuddhabrotChannel] {
+        &self.channels
+    }
+
+    /// Sampled `c` values so far
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Start over with an empty histogram
+    pub fn clear(&mut self) {
+        self.histogram = Vec::new();
+        self.samples = 0;
+        self.pending = 0.0;
+    }
+
+    /// The histogram starts over
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.clear();
+    }
+
+    /// Sample `samples_per_second * delta_time` points on all threads
+    pub fn update(&mut self, delta_time: f64) {
+        self.pending += self.samples_per_second * delta_time;
+        let samples = self.pending.floor();
+        self.pending -= samples;
+        self.sample(samples as u64);
+    }
+
+    /// Add `count` random `c` values to the histogram
+    pub fn sample(&mut self, count: u64) {
+        if count == 0 || self.channels.is_empty() || self.width == 0 || self.height == 0 {
+            return;
+        }
+
+        if self.histogram.is_empty() {
+            self.histogram = (0..self.channels.len() * self.width * self.height).map(|_| AtomicU64::new(0)).collect();
+        }
+
+        let mut rngs = std::mem::take(&mut self.rngs);
+        let per_thread = count.div_ceil(rngs.len().max(1) as u64);
+        let this = &*self;
+        let to_pixels = self.view.to_pixels(self.width, self.height);
+        parallel::for_each(&mut rngs, parallel::thread_count(), |rng| {
+            for _ in 0..per_thread {
+                // Orbits of conjugate points are mirrored, only the upper half is sampled
+                let c = (rng.gen_range(-2.0..1.0), rng.gen_range(0.0..1.5));
+                this.add_orbit(c, &to_pixels);
+            }
+        });
+        self.samples += per_thread * rngs.len() as u64;
+        self.rngs = rngs;
+    }
+
+    /// Tone map the histogram over the whole `target`, which should have the size given to the scene
+    pub fn draw(&self, target: &mut Framebuffer) {
+        let size = self.width * self.height;
+        if target.width() != self.width || target.height() != self.height || self.histogram.is_empty() {
+            return;
+        }
+
+        // Per channel normalization, the channels differ in density by orders of magnitude
+        let scales: Vec<f32> = self
+            .histogram
+            .chunks(size)
+            .map(|channel| {
+                let max = channel.iter().map(|count| count.load(Ordering::Relaxed)).max().unwrap_or(0);
+                if max == 0 { 0.0 } else { self.exposure / max as f32 }
+            })
+            .collect();
+        let colors: Vec<RGB<f32>> = self.channels.iter().map(|channel| channel.color.to_f32()).collect();
+
+        for (i, pixel) in target.pixels_mut().iter_mut().enumerate() {
+            let mut color = RGB::new(0.0, 0.0, 0.0);
+            for (channel, (scale, channel_color)) in scales.iter().zip(&colors).enumerate() {
+                let density = self.histogram[channel * size + i].load(Ordering::Relaxed) as f32 * scale;
+                let intensity = density.min(1.0).powf(self.gamma);
+                color.r += channel_color.r * intensity;
+                color.g += channel_color.g * intensity;
+                color.b += channel_color.b * intensity;
+            }
+            *pixel = color.to_u8().to_pixel();
+        }
+    }
+
+    /// Iterate `c` and, if its orbit qualifies for a channel, add the orbit and its mirror image
+    fn add_orbit(&self, c: (f64, f64), to_pixels: &Transform) {
+        let max_iter = self.channels.iter().map(|channel| channel.max_iter).max().unwrap_or(0);
+        let anti = self.mode == BuddhabrotMode::AntiBuddhabrot;
+        if !anti && in_main_bulbs(c) {
+            return;
+        }
+
+        let iterations = mandelbrot(c.0, c.1, max_iter);
+        // Orbit steps each channel takes, 0 skips the channel
+        let lengths: Vec<u32> = self
+            .channels
+            .iter()
+            .map(|channel| match anti {
+                false if iterations < channel.max_iter && iterations >= channel.min_iter => iterations,
+                true if iterations >= channel.max_iter && channel.max_iter >= channel.min_iter => channel.max_iter,
+                _ => 0,
+            })
+            .collect();
+        let longest = lengths.iter().copied().max().unwrap_or(0);
+        if longest == 0 {
+            return;
+        }
+
+        let size = self.width * self.height;
+        let (mut x, mut y) = (0.0f64, 0.0f64);
+        for step in 1..=longest {
+            let x_temp = x * x - y * y + c.0;
+            y = 2.0 * x * y + c.1;
+            x = x_temp;
+
+            for point in [(x, y), (x, -y)] {
+                let (px, py) = to_pixels.apply(point);
+                if px < 0.0 || py < 0.0 || px >= self.width as f64 || py >= self.height as f64 {
+                    continue;
+                }
+                let index = py as usize * self.width + px as usize;
+                for (channel, &length) in lengths.iter().enumerate() {
+                    if step <= length {
+                        self.histogram[channel * size + index].fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Inside the main cardioid or the period 2 bulb, such points never escape
+#[inline(always)]
+fn in_main_bulbs(c: (f64, f64)) -> bool {
+    let y2 = c.1 * c.1;
+    let q = (c.0 - 0.25) * (c.0 - 0.25) + y2;
+    q * (q + (c.0 - 0.25)) <= 0.25 * y2 || (c.0 + 1.0) * (c.0 + 1.0) + y2 <= 0.0625
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn histogram(scene: &Buddhabrot) -> Vec<u64> {
+        scene.histogram.iter().map(|count| count.load(Ordering::Relaxed)).collect()
+    }
+
+    #[test]
+    fn same_seed_same_histogram() {
+        let mut first = Buddhabrot::nebulabrot(48, 32).with_seed(7);
+        let mut second = Buddhabrot::nebulabrot(48, 32).with_seed(7);
+        first.sample(5000);
+        second.sample(5000);
+
+        assert_eq!(first.samples(), second.samples());
+        assert_eq!(histogram(&first), histogram(&second));
+        assert!(histogram(&first).iter().any(|&count| count > 0));
+    }
+
+    #[test]
+    fn main_bulbs() {
+        for c in [(0.0, 0.0), (0.2, 0.0), (-0.5, 0.5), (-1.0, 0.0), (-1.2, 0.1)] {
+            assert!(in_main_bulbs(c), "{:?}", c);
+        }
+        for c in [(0.3, 0.0), (-1.3, 0.0), (-0.75, 0.1), (0.0, 1.0), (-1.75, 0.0)] {
+            assert!(!in_main_bulbs(c), "{:?}", c);
+        }
+        // Never claims a point that escapes
+        for y in 0..50 {
+            for x in 0..100 {
+                let c = (-2.0 + x as f64 * 0.03, y as f64 * 0.03);
+                assert!(!in_main_bulbs(c) || mandelbrot(c.0, c.1, 1000) == 1000, "{:?}", c);
+            }
+        }
+    }
+
+    #[test]
+    fn anti_buddhabrot_adds_bounded_orbits_only() {
+        let mut scene = Buddhabrot::anti(32, 32).with_view(FractalView::new((0.0, 0.0), 0.5));
+        scene.histogram = (0..32 * 32).map(|_| AtomicU64::new(0)).collect();
+        let to_pixels = scene.view.to_pixels(32, 32);
+
+        // Escapes after a few steps, its first points are still in the view
+        scene.add_orbit((0.5, 0.5), &to_pixels);
+        assert!(histogram(&scene).iter().all(|&count| count == 0));
+
+        scene.add_orbit((-0.1, 0.1), &to_pixels);
+        let expected = 2 * scene.channels[0].max_iter as u64;
+        assert_eq!(histogram(&scene).iter().sum::<u64>(), expected);
+    }
+}
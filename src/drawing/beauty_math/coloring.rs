@@ -0,0 +1,206 @@
+use super::super::colors::colormap::Colormap;
+use super::super::colors::gradient::{Gradient, GradientLut};
+use super::super::colors::RGB;
+
+/// Escape radius for smooth coloring. Large radius makes the normalized iteration count continuous
+pub const BAILOUT: f64 = 256.0;
+
+/// Shape the orbit is measured against
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OrbitTrap {
+    Point((f64, f64)),
+    Circle { center: (f64, f64), radius: f64 },
+    /// Horizontal and vertical lines through the point
+    Cross((f64, f64)),
+}
+
+impl OrbitTrap {
+    #[inline(always)]
+    pub fn distance(&self, z: (f64, f64)) -> f64 {
+        match *self {
+            OrbitTrap::Point(p) => (z.0 - p.0).hypot(z.1 - p.1),
+            OrbitTrap::Circle { center, radius } => ((z.0 - center.0).hypot(z.1 - center.1) - radius).abs(),
+            OrbitTrap::Cross(p) => (z.0 - p.0).abs().min((z.1 - p.1).abs()),
+        }
+    }
+}
+
+/// Result of iterating a point of an escape-time fractal
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Orbit {
+    pub iterations: u32,
+    /// Left [`BAILOUT`] radius before `max_iter`
+    pub escaped: bool,
+    /// Last value of `z`
+    pub z: (f64, f64),
+    /// `dz/dc` at the last iteration, for distance estimation
+    pub derivative: (f64, f64),
+    /// The closest approach to the trap, infinity without a trap
+    pub trap_distance: f64,
+    /// Power of `z` in the formula, 2 for Mandelbrot
+    pub degree: f64,
+    /// Root the point converged to as a gradient position in `[0, 1)`, for root finding fractals
+    pub basin: Option<f64>,
+}
+
+impl Orbit {
+    /// Normalized iteration count, continuous across band boundaries. Only meaningful for escaped points
+    pub fn smooth_iterations(&self) -> f64 {
+        let modulus = self.z.0.hypot(self.z.1).max(1.0 + f64::EPSILON);
+        self.iterations as f64 + 1.0 - modulus.ln().ln() / self.degree.ln()
+    }
+
+    /// Estimated distance to the boundary of the set in complex plane units. Only meaningful for escaped points
+    pub fn distance_estimate(&self) -> f64 {
+        let modulus = self.z.0.hypot(self.z.1);
+        let derivative = self.derivative.0.hypot(self.derivative.1);
+        if derivative == 0.0 {
+            return f64::INFINITY;
+        }
+        0.5 * modulus * modulus.ln() / derivative
+    }
+}
+
+/// Iterate `z = z² + c` tracking the derivative and the trap distance
+#[inline(always)]
+pub fn mandelbrot_orbit(c: (f64, f64), max_iter: u32, trap: Option<&OrbitTrap>) -> Orbit {
+    let (mut x, mut y) = (0.0f64, 0.0f64);
+    let (mut dx, mut dy) = (0.0f64, 0.0f64);
+    let mut trap_distance = f64::INFINITY;
+    let mut i = 0;
+
+    while x * x + y * y <= BAILOUT * BAILOUT && i < max_iter {
+        // dz = 2 z dz + 1
+        let dx_temp = 2.0 * (x * dx - y * dy) + 1.0;
+        dy = 2.0 * (x * dy + y * dx);
+        dx = dx_temp;
+
+        let x_temp = x * x - y * y + c.0;
+        y = 2.0 * x * y + c.1;
+        x = x_temp;
+        i += 1;
+
+        if let Some(trap) = trap {
+            trap_distance = trap_distance.min(trap.distance((x, y)));
+        }
+    }
+
+    Orbit {
+        iterations: i,
+        escaped: i < max_iter,
+        z: (x, y),
+        derivative: (dx, dy),
+        trap_distance,
+        degree: 2.0,
+        basin: None,
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Coloring {
+    /// Integer iteration count, hard bands
+    Bands,
+    /// Normalized iteration count, smooth transitions
+    #[default]
+    Smooth,
+    /// Distance to the boundary, width of the glow in pixels. Keeps thin filaments visible
+    Distance(f64),
+    /// Closest approach of the orbit to the trap, distances from 0 to `scale` use the whole gradient.
+    /// Points inside the set are colored too
+    OrbitTrap { trap: OrbitTrap, scale: f64 },
+    /// Gradient position of [`Orbit::basin`], each iteration multiplies the brightness by the value (e.g. 0.95)
+    Basins(f64),
+}
+
+/// How [`Orbit`]s become pixels.
+///
+/// Example:
+/// ```
+/// let colors = FractalColors::from_colormap(Coloring::Smooth, Colormap::Twilight).with_cycle(64.0);
+/// let pixel = colors.pixel(&orbit, max_iter, view.pixel_size(width, height));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct FractalColors {
+    pub coloring: Coloring,
+    pub lut: GradientLut<RGB<u8>>,
+    /// Iterations per gradient repeat for bands and smooth coloring, 0 stretches the gradient over `max_iter`
+    pub cycle: f64,
+    /// Color of points of the set
+    pub inside: RGB<u8>,
+}
+
+/// Entries of the precomputed gradient
+const LUT_SIZE: usize = 1024;
+
+impl FractalColors {
+    pub fn new(coloring: Coloring, gradient: &Gradient<RGB<u8>>) -> Self {
+        Self { coloring, lut: gradient.to_lut(LUT_SIZE), cycle: 0.0, inside: RGB::new(0, 0, 0) }
+    }
+
+    pub fn from_colormap(coloring: Coloring, colormap: Colormap) -> Self {
+        Self { coloring, lut: colormap.to_lut(LUT_SIZE), cycle: 0.0, inside: RGB::new(0, 0, 0) }
+    }
+
+    pub fn with_cycle(mut self, cycle: f64) -> Self {
+        self.cycle = cycle;
+        self
+    }
+
+    pub fn with_inside(mut self, inside: RGB<u8>) -> Self {
+        self.inside = inside;
+        self
+    }
+
+    /// Trap to track while iterating, if [`Coloring::OrbitTrap`] is used
+    pub fn trap(&self) -> Option<&OrbitTrap> {
+        match &self.coloring {
+            Coloring::OrbitTrap { trap, .. } => Some(trap),
+            _ => None,
+        }
+    }
+
+    /// Gradient position in `[0, 1]`, `None` for points colored with [`Self::inside`]
+    pub fn position(&self, orbit: &Orbit, max_iter: u32, pixel_size: f64) -> Option<f32> {
+        if let Coloring::OrbitTrap { scale, .. } = self.coloring {
+            return Some((orbit.trap_distance / scale).clamp(0.0, 1.0) as f32);
+        }
+        if !orbit.escaped {
+            return None;
+        }
+
+        let position = match self.coloring {
+            Coloring::Bands => self.cycled(orbit.iterations as f64, max_iter),
+            Coloring::Smooth => self.cycled(orbit.smooth_iterations().max(0.0), max_iter),
+            Coloring::Distance(width) => {
+                let pixels = orbit.distance_estimate() / pixel_size;
+                (pixels / width.max(f64::EPSILON)).clamp(0.0, 1.0).sqrt()
+            }
+            Coloring::Basins(_) => orbit.basin?,
+            Coloring::OrbitTrap { .. } => unreachable!(),
+        };
+        Some(position as f32)
+    }
+
+    /// `0x00RRGGBB` pixel for [`super::super::framebuffer::Framebuffer`]
+    #[inline(always)]
+    pub fn pixel(&self, orbit: &Orbit, max_iter: u32, pixel_size: f64) -> u32 {
+        match (self.position(orbit, max_iter, pixel_size), self.coloring) {
+            (Some(position), Coloring::Basins(shade)) => {
+                let brightness = shade.clamp(0.0, 1.0).powi(orbit.iterations as i32) as f32;
+                let RGB { r, g, b } = self.lut.sample(position);
+                let shaded = |channel: u8| (channel as f32 * brightness).round() as u8;
+                RGB::new(shaded(r), shaded(g), shaded(b)).to_pixel()
+            }
+            (Some(position), _) => self.lut.sample(position).to_pixel(),
+            (None, _) => self.inside.to_pixel(),
+        }
+    }
+
+    fn cycled(&self, iterations: f64, max_iter: u32) -> f64 {
+        if self.cycle > 0.0 {
+            (iterations / self.cycle).fract()
+        } else {
+            (iterations / max_iter.max(1) as f64).min(1.0)
+        }
+    }
+}
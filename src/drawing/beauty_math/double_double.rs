@@ -0,0 +1,202 @@
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::str::FromStr;
+
+/// Unevaluated sum of two `f64` with about 32 significant digits, enough for fractal zooms up to ~1e28.
+/// See Hida, Li and Bailey, "Library for Double-Double and Quad-Double Arithmetic"
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+/// `a + b` exactly as `(sum, error)`
+#[inline(always)]
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+/// Like [`two_sum()`], requires `|a| >= |b|`
+#[inline(always)]
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+/// `a * b` exactly as `(product, error)`
+#[inline(always)]
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+    (p, a.mul_add(b, -p))
+}
+
+impl DoubleDouble {
+    pub const ZERO: DoubleDouble = DoubleDouble { hi: 0.0, lo: 0.0 };
+    pub const ONE: DoubleDouble = DoubleDouble { hi: 1.0, lo: 0.0 };
+
+    pub const fn new(hi: f64, lo: f64) -> Self {
+        Self { hi, lo }
+    }
+
+    #[inline(always)]
+    pub fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    #[inline(always)]
+    pub fn mul_f64(self, other: f64) -> Self {
+        let (p, e) = two_prod(self.hi, other);
+        let (hi, lo) = quick_two_sum(p, e + self.lo * other);
+        Self { hi, lo }
+    }
+
+    #[inline(always)]
+    pub fn sqr(self) -> Self {
+        self * self
+    }
+
+    pub fn abs(self) -> Self {
+        if self.hi < 0.0 { -self } else { self }
+    }
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(value: f64) -> Self {
+        Self { hi: value, lo: 0.0 }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, other: Self) -> Self {
+        let (s, e) = two_sum(self.hi, other.hi);
+        let (t, f) = two_sum(self.lo, other.lo);
+        let (s, e) = quick_two_sum(s, e + t);
+        let (hi, lo) = quick_two_sum(s, e + f);
+        Self { hi, lo }
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    #[inline(always)]
+    fn neg(self) -> Self {
+        Self { hi: -self.hi, lo: -self.lo }
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, other: Self) -> Self {
+        let (p, e) = two_prod(self.hi, other.hi);
+        let e = e + (self.hi * other.lo + self.lo * other.hi);
+        let (hi, lo) = quick_two_sum(p, e);
+        Self { hi, lo }
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        // Long division, each step adds about 53 bits
+        let q1 = self.hi / other.hi;
+        let r = self - other.mul_f64(q1);
+        let q2 = r.hi / other.hi;
+        let r = r - other.mul_f64(q2);
+        let q3 = r.hi / other.hi;
+        let (hi, lo) = quick_two_sum(q1, q2);
+        Self { hi, lo } + Self::from(q3)
+    }
+}
+
+impl fmt::Display for DoubleDouble {
+    /// Only the `f64` part, see [`DoubleDouble::hi`] and [`DoubleDouble::lo`] for full precision
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_f64())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseDoubleDoubleError;
+
+impl fmt::Display for ParseDoubleDoubleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid decimal number")
+    }
+}
+
+impl std::error::Error for ParseDoubleDoubleError {}
+
+/// Decimal notation with all digits kept, e.g. `-0.743643887037158704752191506114774` or `1.5e-20`
+impl FromStr for DoubleDouble {
+    type Err = ParseDoubleDoubleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (mantissa, exponent) = match s.find(['e', 'E']) {
+            Some(index) => (&s[..index], s[index + 1..].parse::<i32>().map_err(|_| ParseDoubleDoubleError)?),
+            None => (s, 0),
+        };
+
+        let mut value = DoubleDouble::ZERO;
+        let mut digits = 0;
+        let mut fraction_digits = 0;
+        let mut in_fraction = false;
+        for c in mantissa.chars() {
+            match c {
+                '.' if !in_fraction => in_fraction = true,
+                '0'..='9' => {
+                    value = value.mul_f64(10.0) + DoubleDouble::from((c as u8 - b'0') as f64);
+                    digits += 1;
+                    if in_fraction {
+                        fraction_digits += 1;
+                    }
+                }
+                _ => return Err(ParseDoubleDoubleError),
+            }
+        }
+        if digits == 0 {
+            return Err(ParseDoubleDoubleError);
+        }
+
+        let scale = exponent - fraction_digits;
+        let power = power_of_ten(scale.unsigned_abs());
+        let value = if scale < 0 { value / power } else { value * power };
+        Ok(if negative { -value } else { value })
+    }
+}
+
+/// `10^exponent` by squaring
+fn power_of_ten(mut exponent: u32) -> DoubleDouble {
+    let mut result = DoubleDouble::ONE;
+    let mut base = DoubleDouble::from(10.0);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result * base;
+        }
+        base = base.sqr();
+        exponent >>= 1;
+    }
+    result
+}
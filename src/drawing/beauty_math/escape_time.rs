@@ -0,0 +1,256 @@
+use std::f64::consts::TAU;
+
+use super::coloring::{mandelbrot_orbit, Orbit, OrbitTrap, BAILOUT};
+
+/// Iteration formula of an escape-time fractal. Implementations share [`super::view::FractalView`],
+/// [`super::coloring::FractalColors`] and the parallel renderer, see [`super::render_fractal()`]
+pub trait EscapeTime: Sync {
+    /// Iterate the formula for the complex plane `point` under a pixel
+    fn orbit(&self, point: (f64, f64), max_iter: u32, trap: Option<&OrbitTrap>) -> Orbit;
+}
+
+/// Iterate `step(z, dz) -> (z, dz)` from `z` until it leaves [`BAILOUT`] radius
+#[inline(always)]
+fn escape<F>(
+    mut z: (f64, f64),
+    mut dz: (f64, f64),
+    max_iter: u32,
+    trap: Option<&OrbitTrap>,
+    degree: f64,
+    step: F,
+) -> Orbit
+where
+    F: Fn((f64, f64), (f64, f64)) -> ((f64, f64), (f64, f64)),
+{
+    let mut trap_distance = f64::INFINITY;
+    let mut i = 0;
+
+    while z.0 * z.0 + z.1 * z.1 <= BAILOUT * BAILOUT && i < max_iter {
+        (z, dz) = step(z, dz);
+        i += 1;
+
+        if let Some(trap) = trap {
+            trap_distance = trap_distance.min(trap.distance(z));
+        }
+    }
+
+    Orbit { iterations: i, escaped: i < max_iter, z, derivative: dz, trap_distance, degree, basin: None }
+}
+
+#[inline(always)]
+fn mul(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+/// `z = z² + c`
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Mandelbrot;
+
+impl EscapeTime for Mandelbrot {
+    #[inline(always)]
+    fn orbit(&self, point: (f64, f64), max_iter: u32, trap: Option<&OrbitTrap>) -> Orbit {
+        mandelbrot_orbit(point, max_iter, trap)
+    }
+}
+
+/// `z = z² + c` with fixed `c`, the pixel is the starting `z`.
+/// Moving `c` along a path morphs the set, e.g. [`Julia::at_angle()`] with time
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Julia {
+    pub c: (f64, f64),
+}
+
+impl Default for Julia {
+    fn default() -> Self {
+        Self { c: (-0.8, 0.156) }
+    }
+}
+
+impl Julia {
+    pub const fn new(c: (f64, f64)) -> Self {
+        Self { c }
+    }
+
+    /// `c` on a circle around the origin, radius 0.7885 passes through many connected shapes
+    pub fn at_angle(radius: f64, angle: f64) -> Self {
+        Self { c: (radius * angle.cos(), radius * angle.sin()) }
+    }
+}
+
+impl EscapeTime for Julia {
+    #[inline(always)]
+    fn orbit(&self, point: (f64, f64), max_iter: u32, trap: Option<&OrbitTrap>) -> Orbit {
+        let c = self.c;
+        // Derivative by the starting point: dz = 2 z dz
+        escape(point, (1.0, 0.0), max_iter, trap, 2.0, |z, dz| {
+            let dz = mul(z, dz);
+            ((z.0 * z.0 - z.1 * z.1 + c.0, 2.0 * z.0 * z.1 + c.1), (2.0 * dz.0, 2.0 * dz.1))
+        })
+    }
+}
+
+/// `z = (|re z| + i |im z|)² + c`. The imaginary axis is flipped so the ship stands upright
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct BurningShip;
+
+impl EscapeTime for BurningShip {
+    #[inline(always)]
+    fn orbit(&self, point: (f64, f64), max_iter: u32, trap: Option<&OrbitTrap>) -> Orbit {
+        let c = (point.0, -point.1);
+        // The formula is not analytic, the Mandelbrot derivative of the folded value is a good estimate
+        escape((0.0, 0.0), (0.0, 0.0), max_iter, trap, 2.0, |z, dz| {
+            let (x, y) = (z.0.abs(), z.1.abs());
+            let dz = mul((x, y), dz);
+            ((x * x - y * y + c.0, 2.0 * x * y + c.1), (2.0 * dz.0 + 1.0, 2.0 * dz.1))
+        })
+    }
+}
+
+/// Tricorn (Mandelbar), `z = conj(z)² + c`
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Tricorn;
+
+impl EscapeTime for Tricorn {
+    #[inline(always)]
+    fn orbit(&self, point: (f64, f64), max_iter: u32, trap: Option<&OrbitTrap>) -> Orbit {
+        let c = point;
+        // dz = 2 conj(z) conj(dz) + 1 keeps the magnitude of the derivative right
+        escape((0.0, 0.0), (0.0, 0.0), max_iter, trap, 2.0, |z, dz| {
+            let dz = mul((z.0, -z.1), (dz.0, -dz.1));
+            ((z.0 * z.0 - z.1 * z.1 + c.0, -2.0 * z.0 * z.1 + c.1), (2.0 * dz.0 + 1.0, 2.0 * dz.1))
+        })
+    }
+}
+
+/// `z = z^power + c`, `power` 2 is the Mandelbrot set
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Multibrot {
+    /// Values below 2 are iterated as 2, `z + c` has no fractal and breaks smooth coloring
+    pub power: u32,
+}
+
+impl Default for Multibrot {
+    fn default() -> Self {
+        Self { power: 3 }
+    }
+}
+
+impl Multibrot {
+    /// `power` below 2 is raised to 2
+    pub const fn new(power: u32) -> Self {
+        Self { power: if power < 2 { 2 } else { power } }
+    }
+}
+
+impl EscapeTime for Multibrot {
+    #[inline(always)]
+    fn orbit(&self, point: (f64, f64), max_iter: u32, trap: Option<&OrbitTrap>) -> Orbit {
+        let (c, power) = (point, self.power.max(2));
+        escape((0.0, 0.0), (0.0, 0.0), max_iter, trap, power as f64, |z, dz| {
+            // z^(power - 1) is shared by the value and the derivative: dz = power z^(power - 1) dz + 1
+            let mut z_power = (1.0, 0.0);
+            for _ in 1..power {
+                z_power = mul(z_power, z);
+            }
+            let dz = mul(z_power, dz);
+            let z = mul(z_power, z);
+            ((z.0 + c.0, z.1 + c.1), (power as f64 * dz.0 + 1.0, power as f64 * dz.1))
+        })
+    }
+}
+
+/// Basins of attraction of Newton's method for the polynomial with the given `roots`.
+/// [`Orbit::basin`] tells which root the point converged to, color them with [`super::coloring::Coloring::Basins`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Newton {
+    pub roots: Vec<(f64, f64)>,
+    /// Step multiplier, 1 is the plain method. Other values twist the basin boundaries
+    pub relaxation: (f64, f64),
+    /// Distance to a root that counts as converged
+    pub tolerance: f64,
+}
+
+impl Default for Newton {
+    fn default() -> Self {
+        Self::roots_of_unity(3)
+    }
+}
+
+impl Newton {
+    pub fn new(roots: Vec<(f64, f64)>) -> Self {
+        Self { roots, relaxation: (1.0, 0.0), tolerance: 1e-6 }
+    }
+
+    /// `z^n - 1`
+    pub fn roots_of_unity(n: usize) -> Self {
+        Self::new((0..n).map(|k| (TAU * k as f64 / n as f64).sin_cos()).map(|(sin, cos)| (cos, sin)).collect())
+    }
+
+    pub fn with_relaxation(mut self, relaxation: (f64, f64)) -> Self {
+        self.relaxation = relaxation;
+        self
+    }
+}
+
+impl EscapeTime for Newton {
+    fn orbit(&self, point: (f64, f64), max_iter: u32, trap: Option<&OrbitTrap>) -> Orbit {
+        let mut z = point;
+        let mut trap_distance = f64::INFINITY;
+        let mut basin = None;
+        let mut i = 0;
+
+        while i < max_iter && !self.roots.is_empty() {
+            if let Some(root) = self.roots.iter().position(|r| (z.0 - r.0).hypot(z.1 - r.1) < self.tolerance) {
+                basin = Some(root as f64 / self.roots.len() as f64);
+                break;
+            }
+
+            // p'/p = sum of 1 / (z - root), the step is p/p'
+            let (mut sum_re, mut sum_im) = (0.0, 0.0);
+            for r in &self.roots {
+                let (dx, dy) = (z.0 - r.0, z.1 - r.1);
+                let norm = dx * dx + dy * dy;
+                sum_re += dx / norm;
+                sum_im -= dy / norm;
+            }
+            let norm = sum_re * sum_re + sum_im * sum_im;
+            if norm == 0.0 || !norm.is_finite() {
+                break;
+            }
+            let step = mul(self.relaxation, (sum_re / norm, -sum_im / norm));
+            z = (z.0 - step.0, z.1 - step.1);
+            i += 1;
+
+            if let Some(trap) = trap {
+                trap_distance = trap_distance.min(trap.distance(z));
+            }
+        }
+
+        Orbit {
+            iterations: i,
+            escaped: basin.is_some(),
+            z,
+            derivative: (0.0, 0.0),
+            trap_distance,
+            degree: self.roots.len() as f64,
+            basin,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multibrot_power_below_two_is_mandelbrot() {
+        assert_eq!(Multibrot::new(0), Multibrot::new(2));
+        let point = (0.5, 0.5);
+        for power in [0, 1] {
+            let orbit = Multibrot { power }.orbit(point, 500, None);
+            let mandelbrot = Multibrot::new(2).orbit(point, 500, None);
+            assert!(orbit.escaped && orbit.iterations == mandelbrot.iterations);
+            assert!(orbit.smooth_iterations().is_finite());
+        }
+    }
+}
@@ -0,0 +1,721 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::super::colors::colormap::Colormap;
+use super::super::colors::gradient::{Gradient, GradientLut};
+use super::super::colors::RGB;
+use super::super::framebuffer::Framebuffer;
+use super::super::transform::Transform;
+use super::parallel;
+use super::view::FractalView;
+
+/// Entries of the flame color gradient
+const GRADIENT_SIZE: usize = 256;
+
+/// Iterations skipped after a chain starts, until the point reaches the attractor
+const SKIPPED_ITERATIONS: u32 = 20;
+
+/// Variation functions of Draves and Reckase, "The Fractal Flame Algorithm". Names match flam3
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Variation {
+    Linear,
+    Sinusoidal,
+    Spherical,
+    Swirl,
+    Horseshoe,
+    Polar,
+    Handkerchief,
+    Heart,
+    Disc,
+    Spiral,
+    Hyperbolic,
+    Diamond,
+    Ex,
+    Julia,
+    Bent,
+    Fisheye,
+    Exponential,
+    Power,
+    Cosine,
+    Eyefish,
+    Bubble,
+    Cylinder,
+    Tangent,
+}
+
+impl Variation {
+    pub const ALL: [Variation; 23] = [
+        Variation::Linear,
+        Variation::Sinusoidal,
+        Variation::Spherical,
+        Variation::Swirl,
+        Variation::Horseshoe,
+        Variation::Polar,
+        Variation::Handkerchief,
+        Variation::Heart,
+        Variation::Disc,
+        Variation::Spiral,
+        Variation::Hyperbolic,
+        Variation::Diamond,
+        Variation::Ex,
+        Variation::Julia,
+        Variation::Bent,
+        Variation::Fisheye,
+        Variation::Exponential,
+        Variation::Power,
+        Variation::Cosine,
+        Variation::Eyefish,
+        Variation::Bubble,
+        Variation::Cylinder,
+        Variation::Tangent,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Variation::Linear => "linear",
+            Variation::Sinusoidal => "sinusoidal",
+            Variation::Spherical => "spherical",
+            Variation::Swirl => "swirl",
+            Variation::Horseshoe => "horseshoe",
+            Variation::Polar => "polar",
+            Variation::Handkerchief => "handkerchief",
+            Variation::Heart => "heart",
+            Variation::Disc => "disc",
+            Variation::Spiral => "spiral",
+            Variation::Hyperbolic => "hyperbolic",
+            Variation::Diamond => "diamond",
+            Variation::Ex => "ex",
+            Variation::Julia => "julia",
+            Variation::Bent => "bent",
+            Variation::Fisheye => "fisheye",
+            Variation::Exponential => "exponential",
+            Variation::Power => "power",
+            Variation::Cosine => "cosine",
+            Variation::Eyefish => "eyefish",
+            Variation::Bubble => "bubble",
+            Variation::Cylinder => "cylinder",
+            Variation::Tangent => "tangent",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Variation> {
+        Variation::ALL.into_iter().find(|variation| variation.name().eq_ignore_ascii_case(name))
+    }
+
+    /// `rng` picks one of the two branches of [`Variation::Julia`]
+    #[inline(always)]
+    pub fn apply<R: Rng + ?Sized>(self, (x, y): (f64, f64), rng: &mut R) -> (f64, f64) {
+        let r2 = x * x + y * y;
+        let r = r2.sqrt();
+        // Angle from the y axis as in the paper
+        let theta = x.atan2(y);
+
+        match self {
+            Variation::Linear => (x, y),
+            Variation::Sinusoidal => (x.sin(), y.sin()),
+            Variation::Spherical => (x / r2, y / r2),
+            Variation::Swirl => {
+                let (sin, cos) = r2.sin_cos();
+                (x * sin - y * cos, x * cos + y * sin)
+            }
+            Variation::Horseshoe => ((x - y) * (x + y) / r, 2.0 * x * y / r),
+            Variation::Polar => (theta / PI, r - 1.0),
+            Variation::Handkerchief => (r * (theta + r).sin(), r * (theta - r).cos()),
+            Variation::Heart => (r * (theta * r).sin(), -r * (theta * r).cos()),
+            Variation::Disc => {
+                let (sin, cos) = (PI * r).sin_cos();
+                (theta / PI * sin, theta / PI * cos)
+            }
+            Variation::Spiral => ((theta.cos() + r.sin()) / r, (theta.sin() - r.cos()) / r),
+            Variation::Hyperbolic => (theta.sin() / r, r * theta.cos()),
+            Variation::Diamond => (theta.sin() * r.cos(), theta.cos() * r.sin()),
+            Variation::Ex => {
+                let p0 = (theta + r).sin().powi(3);
+                let p1 = (theta - r).cos().powi(3);
+                (r * (p0 + p1), r * (p0 - p1))
+            }
+            Variation::Julia => {
+                let omega = if rng.gen::<bool>() { PI } else { 0.0 };
+                let (sin, cos) = (theta / 2.0 + omega).sin_cos();
+                (r.sqrt() * cos, r.sqrt() * sin)
+            }
+            Variation::Bent => match (x >= 0.0, y >= 0.0) {
+                (true, true) => (x, y),
+                (false, true) => (2.0 * x, y),
+                (true, false) => (x, y / 2.0),
+                (false, false) => (2.0 * x, y / 2.0),
+            },
+            Variation::Fisheye => (2.0 / (r + 1.0) * y, 2.0 / (r + 1.0) * x),
+            Variation::Exponential => {
+                let (sin, cos) = (PI * y).sin_cos();
+                let scale = (x - 1.0).exp();
+                (scale * cos, scale * sin)
+            }
+            Variation::Power => {
+                let scale = r.powf(theta.sin());
+                (scale * theta.cos(), scale * theta.sin())
+            }
+            Variation::Cosine => ((PI * x).cos() * y.cosh(), -(PI * x).sin() * y.sinh()),
+            Variation::Eyefish => (2.0 / (r + 1.0) * x, 2.0 / (r + 1.0) * y),
+            Variation::Bubble => (4.0 / (r2 + 4.0) * x, 4.0 / (r2 + 4.0) * y),
+            Variation::Cylinder => (x.sin(), y),
+            Variation::Tangent => (x.sin() / y.cos(), y.tan()),
+        }
+    }
+}
+
+impl fmt::Display for Variation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// One function of the system: affine map, blend of variations, then the post transform
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlameTransform {
+    /// Relative probability of being picked
+    pub weight: f64,
+    /// Gradient position the points move towards
+    pub color: f64,
+    /// How far the point color moves towards [`Self::color`] on each pick, 0.5 in the paper
+    pub color_speed: f64,
+    pub affine: Transform,
+    pub variations: Vec<(Variation, f64)>,
+    pub post: Transform,
+}
+
+impl FlameTransform {
+    /// Plain affine map, as in classic IFS
+    pub fn affine(affine: Transform, weight: f64, color: f64) -> Self {
+        Self {
+            weight,
+            color,
+            color_speed: 0.5,
+            affine,
+            variations: vec![(Variation::Linear, 1.0)],
+            post: Transform::IDENTITY,
+        }
+    }
+
+    pub fn with_variations(mut self, variations: Vec<(Variation, f64)>) -> Self {
+        self.variations = variations;
+        self
+    }
+
+    pub fn with_post(mut self, post: Transform) -> Self {
+        self.post = post;
+        self
+    }
+
+    #[inline(always)]
+    pub fn apply<R: Rng + ?Sized>(&self, point: (f64, f64), rng: &mut R) -> (f64, f64) {
+        let point = self.affine.apply(point);
+        let (mut x, mut y) = (0.0, 0.0);
+        for &(variation, amount) in &self.variations {
+            let (vx, vy) = variation.apply(point, rng);
+            x += amount * vx;
+            y += amount * vy;
+        }
+        self.post.apply((x, y))
+    }
+
+    #[inline(always)]
+    fn blend_color(&self, color: f64) -> f64 {
+        color + (self.color - color) * self.color_speed
+    }
+}
+
+#[derive(Debug)]
+pub enum FlameError {
+    Io(std::io::Error),
+    /// Malformed line of the description, 1-based
+    InvalidLine(usize),
+    UnknownVariation(String),
+    /// No transforms, or all weights are 0
+    Empty,
+}
+
+impl fmt::Display for FlameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlameError::Io(error) => write!(f, "Could not read the flame file: {}", error),
+            FlameError::InvalidLine(line) => write!(f, "Invalid flame description on line {}", line),
+            FlameError::UnknownVariation(name) => write!(f, "Unknown variation: {}", name),
+            FlameError::Empty => write!(f, "Flame has no transforms"),
+        }
+    }
+}
+
+impl std::error::Error for FlameError {}
+
+impl From<std::io::Error> for FlameError {
+    fn from(error: std::io::Error) -> Self {
+        FlameError::Io(error)
+    }
+}
+
+/// Fractal flame or iterated function system, rendered with the chaos game by [`FlameScene`].
+///
+/// Description format, one statement per line, `# ` starts a comment:
+/// ```text
+/// name Spiral galaxy
+/// view 0 0 0.8 0.3                  # center re, im, zoom and optional rotation, see FractalView
+/// colormap magma                    # or: gradient #000000 #ff8000 #ffffff
+/// transform weight 0.7 color 0 affine 0.8 0.3 -0.3 0.8 0 0 swirl 0.6 linear 0.4
+/// transform weight 0.3 color 1 speed 0.8 affine 0.5 0 0 0.5 0.5 0.2 spherical 1 post 1 0 0 1 0 0.1
+/// final affine 1 0 0 1 0 0 julia 1
+/// ```
+/// `affine` and `post` take `a b c d e f` of [`Transform`] (same order as flam3 `coefs`),
+/// a transform without variations is linear. Weights must not be negative. Unknown keywords are errors
+#[derive(Clone, Debug, PartialEq)]
+pub struct Flame {
+    pub name: Option<String>,
+    pub transforms: Vec<FlameTransform>,
+    /// Applied to every point before plotting, does not feed back into the iteration
+    pub final_transform: Option<FlameTransform>,
+    pub gradient: GradientLut<RGB<u8>>,
+    pub view: FractalView,
+}
+
+impl Flame {
+    pub fn new(transforms: Vec<FlameTransform>, gradient: &Gradient<RGB<u8>>, view: FractalView) -> Self {
+        Self { name: None, transforms, final_transform: None, gradient: gradient.to_lut(GRADIENT_SIZE), view }
+    }
+
+    /// Classic IFS of affine `maps` with their probabilities, colored by the map that placed the point
+    pub fn ifs(maps: &[(Transform, f64)], colormap: Colormap, view: FractalView) -> Self {
+        let count = maps.len().saturating_sub(1).max(1) as f64;
+        let transforms = maps
+            .iter()
+            .enumerate()
+            .map(|(i, &(affine, weight))| FlameTransform::affine(affine, weight, i as f64 / count))
+            .collect();
+        Self { name: None, transforms, final_transform: None, gradient: colormap.to_lut(GRADIENT_SIZE), view }
+    }
+
+    pub fn barnsley_fern() -> Self {
+        let maps = [
+            (Transform::new(0.0, 0.0, 0.0, 0.16, 0.0, 0.0), 0.01),
+            (Transform::new(0.85, -0.04, 0.04, 0.85, 0.0, 1.6), 0.85),
+            (Transform::new(0.2, 0.23, -0.26, 0.22, 0.0, 1.6), 0.07),
+            (Transform::new(-0.15, 0.26, 0.28, 0.24, 0.0, 0.44), 0.07),
+        ];
+        let fern = Flame::ifs(&maps, Colormap::Viridis, FractalView::new((0.0, 5.0), 0.38));
+        fern.with_name("Barnsley fern")
+    }
+
+    pub fn sierpinski() -> Self {
+        let maps = [
+            (Transform::new(0.5, 0.0, 0.0, 0.5, 0.0, 0.0), 1.0),
+            (Transform::new(0.5, 0.0, 0.0, 0.5, 0.5, 0.0), 1.0),
+            (Transform::new(0.5, 0.0, 0.0, 0.5, 0.25, 0.433), 1.0),
+        ];
+        let triangle = Flame::ifs(&maps, Colormap::Plasma, FractalView::new((0.5, 0.43), 3.6));
+        triangle.with_name("Sierpinski triangle")
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn with_final(mut self, final_transform: FlameTransform) -> Self {
+        self.final_transform = Some(final_transform);
+        self
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Flame, FlameError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// Transform picked with probability proportional to its weight, `choice` in `[0, total weight)`
+    #[inline(always)]
+    fn pick(&self, mut choice: f64) -> &FlameTransform {
+        for transform in &self.transforms {
+            if choice < transform.weight {
+                return transform;
+            }
+            choice -= transform.weight;
+        }
+        &self.transforms[self.transforms.len() - 1]
+    }
+
+    fn total_weight(&self) -> f64 {
+        self.transforms.iter().map(|transform| transform.weight.max(0.0)).sum()
+    }
+}
+
+impl FromStr for Flame {
+    type Err = FlameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut flame = Flame::ifs(&[], Colormap::Inferno, FractalView::default());
+
+        for (index, line) in s.lines().enumerate() {
+            let line_number = index + 1;
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let invalid = || FlameError::InvalidLine(line_number);
+
+            match keyword {
+                "name" => flame.name = Some(rest.trim().to_string()),
+                "view" => {
+                    let values = rest.split_whitespace().map(|value| value.parse::<f64>()).collect::<Result<Vec<_>, _>>();
+                    let values = values.map_err(|_| invalid())?;
+                    if values.len() < 3 || values.len() > 4 {
+                        return Err(invalid());
+                    }
+                    flame.view = FractalView::new((values[0], values[1]), values[2])
+                        .with_rotation(values.get(3).copied().unwrap_or(0.0));
+                }
+                "colormap" => {
+                    let colormap: Colormap = rest.trim().parse().map_err(|_| invalid())?;
+                    flame.gradient = colormap.to_lut(GRADIENT_SIZE);
+                }
+                "gradient" => {
+                    let colors = rest
+                        .split_whitespace()
+                        .map(|color| color.parse::<RGB<u8>>().map_err(|_| invalid()))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if colors.is_empty() {
+                        return Err(invalid());
+                    }
+                    flame.gradient = Gradient::even(&colors).to_lut(GRADIENT_SIZE);
+                }
+                "transform" => flame.transforms.push(parse_transform(rest, line_number, false)?),
+                "final" => flame.final_transform = Some(parse_transform(rest, line_number, true)?),
+                _ => return Err(invalid()),
+            }
+        }
+
+        if flame.transforms.is_empty() || flame.total_weight() <= 0.0 {
+            return Err(FlameError::Empty);
+        }
+        Ok(flame)
+    }
+}
+
+/// `#` followed by a space or the line end starts a comment, `#rrggbb` colors do not
+fn strip_comment(line: &str) -> &str {
+    let comment = line.match_indices('#').find(|&(index, _)| {
+        line[index + 1..].chars().next().is_none_or(char::is_whitespace)
+    });
+    comment.map_or(line, |(index, _)| &line[..index])
+}
+
+/// `key value` pairs of a `transform` or `final` line. Like in flam3 the `final` transform keeps the point color
+/// unless `color` or `speed` is given
+fn parse_transform(s: &str, line_number: usize, is_final: bool) -> Result<FlameTransform, FlameError> {
+    let invalid = || FlameError::InvalidLine(line_number);
+    let mut transform = FlameTransform::affine(Transform::IDENTITY, 1.0, 0.0).with_variations(Vec::new());
+    let mut tokens = s.split_whitespace();
+    let (mut color, mut speed) = (None, None);
+    let number = |tokens: &mut std::str::SplitWhitespace| -> Result<f64, FlameError> {
+        tokens.next().and_then(|value| value.parse().ok()).ok_or_else(invalid)
+    };
+    let matrix = |tokens: &mut std::str::SplitWhitespace| -> Result<Transform, FlameError> {
+        let mut values = [0.0; 6];
+        for value in values.iter_mut() {
+            *value = tokens.next().and_then(|value| value.parse().ok()).ok_or_else(invalid)?;
+        }
+        Ok(Transform::new(values[0], values[1], values[2], values[3], values[4], values[5]))
+    };
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "weight" => {
+                transform.weight = number(&mut tokens)?;
+                if transform.weight < 0.0 || !transform.weight.is_finite() {
+                    return Err(invalid());
+                }
+            }
+            "color" => color = Some(number(&mut tokens)?),
+            "speed" => speed = Some(number(&mut tokens)?),
+            "affine" => transform.affine = matrix(&mut tokens)?,
+            "post" => transform.post = matrix(&mut tokens)?,
+            name => {
+                let variation = Variation::from_name(name);
+                let variation = variation.ok_or_else(|| FlameError::UnknownVariation(name.to_string()))?;
+                transform.variations.push((variation, number(&mut tokens)?));
+            }
+        }
+    }
+
+    if transform.variations.is_empty() {
+        transform.variations.push((Variation::Linear, 1.0));
+    }
+    if is_final && color.is_none() && speed.is_none() {
+        transform.color_speed = 0.0;
+    }
+    transform.color = color.unwrap_or(transform.color);
+    transform.color_speed = speed.unwrap_or(transform.color_speed);
+    Ok(transform)
+}
+
+/// Chaos game state of one thread
+#[derive(Clone, Debug)]
+struct Walker {
+    rng: StdRng,
+    point: (f64, f64),
+    color: f64,
+    /// Iterations left before points are plotted
+    skip: u32,
+}
+
+impl Walker {
+    fn new(seed: u64) -> Self {
+        let mut walker = Self { rng: StdRng::seed_from_u64(seed), point: (0.0, 0.0), color: 0.0, skip: 0 };
+        walker.restart();
+        walker
+    }
+
+    fn restart(&mut self) {
+        self.point = (self.rng.gen_range(-1.0..1.0), self.rng.gen_range(-1.0..1.0));
+        self.color = self.rng.gen();
+        self.skip = SKIPPED_ITERATIONS;
+    }
+}
+
+/// Renders a [`Flame`] progressively: every update runs more of the chaos game, the image sharpens over time.
+/// Hits are accumulated with their gradient color and tone mapped by log density.
+///
+/// Example:
+/// ```
+/// let mut scene = FlameScene::new(Flame::from_file("galaxy.flame")?, width, height);
+/// // every frame
+/// scene.update(delta_time);
+/// scene.draw(&mut framebuffer);
+/// ```
+#[derive(Debug)]
+pub struct FlameScene {
+    /// Chaos game iterations per second of [`Self::update()`]
+    pub iterations_per_second: f64,
+    /// Multiplier of the tone mapped image
+    pub brightness: f32,
+    /// Applied to the log density, higher values bring out sparse areas
+    pub gamma: f32,
+    flame: Flame,
+    width: usize,
+    height: usize,
+    /// Hits and sums of red, green and blue per pixel, allocated on the first update
+    histogram: Vec<[AtomicU64; 4]>,
+    walkers: Vec<Walker>,
+    iterations: u64,
+    /// Fractional iterations carried to the next update
+    pending: f64,
+}
+
+impl FlameScene {
+    pub fn new(flame: Flame, width: usize, height: usize) -> Self {
+        Self {
+            iterations_per_second: 1_000_000.0,
+            brightness: 1.0,
+            gamma: 2.2,
+            flame,
+            width,
+            height,
+            histogram: Vec::new(),
+            walkers: Vec::new(),
+            iterations: 0,
+            pending: 0.0,
+        }
+        .with_seed(rand::thread_rng().gen())
+    }
+
+    /// Same seed gives the same image after the same number of updates
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.walkers = (0..parallel::thread_count()).map(|_| Walker::new(rng.gen())).collect();
+        self.clear();
+        self
+    }
+
+    pub fn flame(&self) -> &Flame {
+        &self.flame
+    }
+
+    /// The image starts over
+    pub fn set_flame(&mut self, flame: Flame) {
+        self.flame = flame;
+        self.clear();
+    }
+
+    /// Chaos game iterations so far
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    pub fn clear(&mut self) {
+        self.histogram = Vec::new();
+        self.iterations = 0;
+        self.pending = 0.0;
+    }
+
+    /// The image starts over
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.clear();
+    }
+
+    /// Run `iterations_per_second * delta_time` iterations on all threads
+    pub fn update(&mut self, delta_time: f64) {
+        self.pending += self.iterations_per_second * delta_time;
+        let iterations = self.pending.floor();
+        self.pending -= iterations;
+        self.iterate(iterations as u64);
+    }
+
+    /// Run `count` chaos game iterations
+    pub fn iterate(&mut self, count: u64) {
+        let total_weight = self.flame.total_weight();
+        if count == 0 || total_weight <= 0.0 || self.width == 0 || self.height == 0 {
+            return;
+        }
+        if self.histogram.is_empty() {
+            self.histogram = (0..self.width * self.height).map(|_| std::array::from_fn(|_| AtomicU64::new(0))).collect();
+        }
+
+        let mut walkers = std::mem::take(&mut self.walkers);
+        let per_thread = count.div_ceil(walkers.len().max(1) as u64);
+        let this = &*self;
+        let to_pixels = self.flame.view.to_pixels(self.width, self.height);
+        parallel::for_each(&mut walkers, parallel::thread_count(), |walker| {
+            for _ in 0..per_thread {
+                this.step(walker, total_weight, &to_pixels);
+            }
+        });
+        self.iterations += per_thread * walkers.len() as u64;
+        self.walkers = walkers;
+    }
+
+    /// Tone map over the whole `target`, which should have the size given to the scene
+    pub fn draw(&self, target: &mut Framebuffer) {
+        if target.width() != self.width || target.height() != self.height || self.histogram.is_empty() {
+            return;
+        }
+
+        let max = self.histogram.iter().map(|pixel| pixel[0].load(Ordering::Relaxed)).max().unwrap_or(0);
+        let log_max = (max as f32).ln_1p().max(f32::EPSILON);
+        for (pixel, bin) in target.pixels_mut().iter_mut().zip(&self.histogram) {
+            let hits = bin[0].load(Ordering::Relaxed);
+            if hits == 0 {
+                *pixel = 0;
+                continue;
+            }
+            // Average color of the hits, brightness from the log of their number
+            let alpha = ((hits as f32).ln_1p() / log_max).powf(1.0 / self.gamma) * self.brightness;
+            let channel = |sum: &AtomicU64| sum.load(Ordering::Relaxed) as f32 / hits as f32 / 255.0 * alpha;
+            *pixel = RGB::new(channel(&bin[1]), channel(&bin[2]), channel(&bin[3])).to_u8().to_pixel();
+        }
+    }
+
+    #[inline(always)]
+    fn step(&self, walker: &mut Walker, total_weight: f64, to_pixels: &Transform) {
+        let transform = self.flame.pick(walker.rng.gen::<f64>() * total_weight);
+        walker.point = transform.apply(walker.point, &mut walker.rng);
+        walker.color = transform.blend_color(walker.color);
+        if !walker.point.0.is_finite() || !walker.point.1.is_finite() {
+            walker.restart();
+            return;
+        }
+        if walker.skip > 0 {
+            walker.skip -= 1;
+            return;
+        }
+
+        let (point, color) = match &self.flame.final_transform {
+            Some(final_transform) => {
+                (final_transform.apply(walker.point, &mut walker.rng), final_transform.blend_color(walker.color))
+            }
+            None => (walker.point, walker.color),
+        };
+        let (x, y) = to_pixels.apply(point);
+        if !(x >= 0.0 && y >= 0.0 && x < self.width as f64 && y < self.height as f64) {
+            return;
+        }
+
+        let bin = &self.histogram[y as usize * self.width + x as usize];
+        let RGB { r, g, b } = self.flame.gradient.sample(color as f32);
+        bin[0].fetch_add(1, Ordering::Relaxed);
+        bin[1].fetch_add(r as u64, Ordering::Relaxed);
+        bin[2].fetch_add(g as u64, Ordering::Relaxed);
+        bin[3].fetch_add(b as u64, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "name Spiral galaxy
+view 0 0 0.8 0.3                  # center re, im, zoom and optional rotation, see FractalView
+colormap magma                    # or: gradient #000000 #ff8000 #ffffff
+transform weight 0.7 color 0 affine 0.8 0.3 -0.3 0.8 0 0 swirl 0.6 linear 0.4
+transform weight 0.3 color 1 speed 0.8 affine 0.5 0 0 0.5 0.5 0.2 spherical 1 post 1 0 0 1 0 0.1
+final affine 1 0 0 1 0 0 julia 1
+";
+
+    #[test]
+    fn parse_example() {
+        let flame: Flame = EXAMPLE.parse().unwrap();
+        assert_eq!(flame.name.as_deref(), Some("Spiral galaxy"));
+        assert_eq!(flame.view, FractalView::new((0.0, 0.0), 0.8).with_rotation(0.3));
+        assert_eq!(flame.gradient, Colormap::Magma.to_lut(GRADIENT_SIZE));
+
+        assert_eq!(flame.transforms.len(), 2);
+        let first = &flame.transforms[0];
+        assert_eq!((first.weight, first.color, first.color_speed), (0.7, 0.0, 0.5));
+        assert_eq!(first.affine, Transform::new(0.8, 0.3, -0.3, 0.8, 0.0, 0.0));
+        assert_eq!(first.variations, vec![(Variation::Swirl, 0.6), (Variation::Linear, 0.4)]);
+        let second = &flame.transforms[1];
+        assert_eq!((second.weight, second.color, second.color_speed), (0.3, 1.0, 0.8));
+        assert_eq!(second.post, Transform::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.1));
+
+        let final_transform = flame.final_transform.unwrap();
+        assert_eq!(final_transform.variations, vec![(Variation::Julia, 1.0)]);
+        assert_eq!(final_transform.color_speed, 0.0);
+    }
+
+    #[test]
+    fn final_blends_only_with_color() {
+        let flame: Flame = "transform linear 1\nfinal color 0.5 linear 1".parse().unwrap();
+        assert_eq!(flame.final_transform.unwrap().color_speed, 0.5);
+    }
+
+    #[test]
+    fn hex_colors_are_not_comments() {
+        let flame: Flame = "gradient #000000 #ff8000 # warm\ntransform linear 1".parse().unwrap();
+        let colors = [RGB::new(0, 0, 0), RGB::new(255, 128, 0)];
+        assert_eq!(flame.gradient, Gradient::even(&colors).to_lut(GRADIENT_SIZE));
+
+        assert_eq!(strip_comment("gradient #ff8000 #ffffff"), "gradient #ff8000 #ffffff");
+        assert_eq!(strip_comment("view 0 0 1 # comment"), "view 0 0 1 ");
+        assert_eq!(strip_comment("colormap magma #"), "colormap magma ");
+    }
+
+    #[test]
+    fn errors() {
+        let error = |s: &str| s.parse::<Flame>().unwrap_err().to_string();
+        let line = |n| FlameError::InvalidLine(n).to_string();
+
+        assert_eq!(error("name a\n\n# comment\nview 0 0"), line(4));
+        assert_eq!(error("transform linear 1\ncolormap nope"), line(2));
+        assert_eq!(error("transform weight -1 linear 1"), line(1));
+        assert_eq!(error("transform weight inf linear 1"), line(1));
+        assert_eq!(error("transform affine 1 0 0 1"), line(1));
+        assert_eq!(error("shape square"), line(1));
+        assert_eq!(error("transform warp 1"), FlameError::UnknownVariation("warp".to_string()).to_string());
+        assert_eq!(error("name empty"), FlameError::Empty.to_string());
+        assert_eq!(error("transform weight 0 linear 1"), FlameError::Empty.to_string());
+    }
+}
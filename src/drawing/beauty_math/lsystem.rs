@@ -0,0 +1,843 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::super::canvas::{Canvas, Surface};
+use super::super::colors::{RGB, interpolate_colors};
+
+/// Generations stop before the string gets longer, a few more would not fit in memory
+const MAX_MODULES: usize = 2_000_000;
+
+/// Lines thinner than this many pixels are drawn as hairlines
+const HAIRLINE_WIDTH: f64 = 1.5;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LSystemError {
+    InvalidAxiom(String),
+    InvalidRule(String),
+    /// Rule uses a parameter its predecessor does not declare
+    UnknownParameter(String),
+}
+
+impl fmt::Display for LSystemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LSystemError::InvalidAxiom(axiom) => write!(f, "Invalid axiom: {}", axiom),
+            LSystemError::InvalidRule(rule) => write!(f, "Invalid rule: {}", rule),
+            LSystemError::UnknownParameter(name) => write!(f, "Unknown parameter: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for LSystemError {}
+
+/// Symbol with its actual parameters, e.g. `F(2.5)`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Module {
+    pub symbol: char,
+    pub params: Vec<f64>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+/// Arithmetic over the parameters of the predecessor, comparisons give 1 or 0
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Number(f64),
+    /// Index of the parameter in the predecessor
+    Param(usize),
+    Neg(Box<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, params: &[f64]) -> f64 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Param(index) => params[*index],
+            Expr::Neg(expr) => -expr.eval(params),
+            Expr::Binary(operator, left, right) => {
+                let (a, b) = (left.eval(params), right.eval(params));
+                let truth = |value: bool| if value { 1.0 } else { 0.0 };
+                match operator {
+                    Operator::Add => a + b,
+                    Operator::Sub => a - b,
+                    Operator::Mul => a * b,
+                    Operator::Div => a / b,
+                    Operator::Pow => a.powf(b),
+                    Operator::Less => truth(a < b),
+                    Operator::LessEqual => truth(a <= b),
+                    Operator::Greater => truth(a > b),
+                    Operator::GreaterEqual => truth(a >= b),
+                    Operator::Equal => truth(a == b),
+                    Operator::NotEqual => truth(a != b),
+                    Operator::And => truth(a != 0.0 && b != 0.0),
+                    Operator::Or => truth(a != 0.0 || b != 0.0),
+                }
+            }
+        }
+    }
+}
+
+/// Recursive descent over modules and expressions. Errors carry no position, the caller reports the whole text
+struct Parser<'a> {
+    chars: Vec<char>,
+    position: usize,
+    /// Formal parameters of the predecessor
+    params: &'a [String],
+}
+
+#[derive(Debug)]
+enum ParseError {
+    Syntax,
+    UnknownParameter(String),
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &str, params: &'a [String]) -> Self {
+        Self { chars: text.chars().collect(), position: 0, params }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.get(self.position).is_some_and(|c| c.is_whitespace()) {
+            self.position += 1;
+        }
+        self.chars.get(self.position).copied()
+    }
+
+    /// Consume `token` if it comes next
+    fn eat(&mut self, token: &str) -> bool {
+        self.peek();
+        let matches = token.chars().enumerate().all(|(i, c)| self.chars.get(self.position + i) == Some(&c));
+        if matches {
+            self.position += token.chars().count();
+        }
+        matches
+    }
+
+    /// Symbols, each optionally followed by `(expr, ...)`
+    fn modules(&mut self) -> Result<Vec<(char, Vec<Expr>)>, ParseError> {
+        let mut modules = Vec::new();
+        while let Some(symbol) = self.peek() {
+            if matches!(symbol, '(' | ')' | ',') {
+                return Err(ParseError::Syntax);
+            }
+            self.position += 1;
+
+            let mut args = Vec::new();
+            if self.chars.get(self.position) == Some(&'(') {
+                self.position += 1;
+                loop {
+                    args.push(self.expression()?);
+                    if self.eat(")") {
+                        break;
+                    }
+                    if !self.eat(",") {
+                        return Err(ParseError::Syntax);
+                    }
+                }
+            }
+            modules.push((symbol, args));
+        }
+        Ok(modules)
+    }
+
+    /// Whole remaining text as one expression
+    fn condition(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.expression()?;
+        match self.peek() {
+            None => Ok(expr),
+            Some(_) => Err(ParseError::Syntax),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.conjunction()?;
+        while self.eat("||") {
+            left = Expr::Binary(Operator::Or, Box::new(left), Box::new(self.conjunction()?));
+        }
+        Ok(left)
+    }
+
+    fn conjunction(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.comparison()?;
+        while self.eat("&&") {
+            left = Expr::Binary(Operator::And, Box::new(left), Box::new(self.comparison()?));
+        }
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let left = self.sum()?;
+        // Two character operators first, `<` would match the start of `<=`
+        let operators = [
+            ("<=", Operator::LessEqual),
+            (">=", Operator::GreaterEqual),
+            ("==", Operator::Equal),
+            ("!=", Operator::NotEqual),
+            ("<", Operator::Less),
+            (">", Operator::Greater),
+            ("=", Operator::Equal),
+        ];
+        for (token, operator) in operators {
+            if self.eat(token) {
+                return Ok(Expr::Binary(operator, Box::new(left), Box::new(self.sum()?)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.product()?;
+        loop {
+            let operator = if self.eat("+") {
+                Operator::Add
+            } else if self.eat("-") {
+                Operator::Sub
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Binary(operator, Box::new(left), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.unary()?;
+        loop {
+            let operator = if self.eat("*") {
+                Operator::Mul
+            } else if self.eat("/") {
+                Operator::Div
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Binary(operator, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        let base = self.atom()?;
+        if self.eat("^") {
+            // Right associative, `-2^2` is `-(2^2)`
+            return Ok(Expr::Binary(Operator::Pow, Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Expr, ParseError> {
+        let start = match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let expr = self.expression()?;
+                return if self.eat(")") { Ok(expr) } else { Err(ParseError::Syntax) };
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' || c.is_alphabetic() || c == '_' => self.position,
+            _ => return Err(ParseError::Syntax),
+        };
+
+        let is_number = !self.chars[start].is_alphabetic() && self.chars[start] != '_';
+        while let Some(&c) = self.chars.get(self.position) {
+            // Exponent of a number, `1e-3`
+            if is_number && (c == 'e' || c == 'E') {
+                let mut next = self.position + 1;
+                if matches!(self.chars.get(next), Some('+' | '-')) {
+                    next += 1;
+                }
+                if !self.chars.get(next).is_some_and(char::is_ascii_digit) {
+                    break;
+                }
+                self.position = next;
+                continue;
+            }
+            let continues = if is_number { c.is_ascii_digit() || c == '.' } else { c.is_alphanumeric() || c == '_' };
+            if !continues {
+                break;
+            }
+            self.position += 1;
+        }
+
+        let token: String = self.chars[start..self.position].iter().collect();
+        if is_number {
+            return token.parse().map(Expr::Number).map_err(|_| ParseError::Syntax);
+        }
+        match self.params.iter().position(|name| *name == token) {
+            Some(index) => Ok(Expr::Param(index)),
+            None => Err(ParseError::UnknownParameter(token)),
+        }
+    }
+}
+
+/// Production `predecessor(params) : condition -(weight)-> successor`, only the predecessor and successor are required.
+///
+/// ```text
+/// F -> F[+F]F[-F]F                            # deterministic
+/// F -(0.33)-> F[+F]F                          # stochastic, rules of the same symbol are picked by weight
+/// A(l, w) : l > 0.02 -> !(w)F(l)[+(30)A(l * 0.75, w * 0.7)]
+/// ```
+/// Expressions support `+ - * / ^`, parentheses, comparisons and `&& ||`, numbers like `0.5` or `1e-3`.
+/// The weight must touch the arrow, `-(2) ->` with a space is part of the condition
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub predecessor: char,
+    /// Relative probability among the rules that match the same module
+    pub weight: f64,
+    params: Vec<String>,
+    condition: Option<Expr>,
+    successor: Vec<(char, Vec<Expr>)>,
+}
+
+impl Rule {
+    /// Same symbol and parameter count, and the condition holds
+    fn matches(&self, module: &Module) -> bool {
+        self.predecessor == module.symbol
+            && self.params.len() == module.params.len()
+            && self.condition.as_ref().is_none_or(|condition| condition.eval(&module.params) != 0.0)
+    }
+
+    fn apply(&self, module: &Module, output: &mut Vec<Module>) {
+        output.extend(self.successor.iter().map(|(symbol, args)| Module {
+            symbol: *symbol,
+            params: args.iter().map(|arg| arg.eval(&module.params)).collect(),
+        }));
+    }
+}
+
+impl FromStr for Rule {
+    type Err = LSystemError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || LSystemError::InvalidRule(text.to_string());
+        let reported = |error: ParseError| match error {
+            ParseError::Syntax => invalid(),
+            ParseError::UnknownParameter(name) => LSystemError::UnknownParameter(name),
+        };
+
+        let arrow = text.find("->").ok_or_else(invalid)?;
+        let (mut left, successor) = (&text[..arrow], &text[arrow + 2..]);
+
+        let mut weight = 1.0;
+        if let Some(open) = left.rfind("-(").filter(|_| left.ends_with(')')) {
+            if let Ok(value) = left[open + 2..left.len() - 1].trim().parse::<f64>() {
+                weight = value;
+                left = &left[..open];
+            }
+        }
+        if weight < 0.0 || !weight.is_finite() {
+            return Err(invalid());
+        }
+
+        let (predecessor, condition) = match left.split_once(':') {
+            Some((predecessor, condition)) => (predecessor, Some(condition)),
+            None => (left, None),
+        };
+
+        // The predecessor is a symbol with names instead of expressions
+        let predecessor = predecessor.trim();
+        let mut chars = predecessor.chars();
+        let symbol = chars.next().ok_or_else(invalid)?;
+        let rest = chars.as_str().trim();
+        let params: Vec<String> = if rest.is_empty() {
+            Vec::new()
+        } else {
+            let names = rest.strip_prefix('(').and_then(|rest| rest.strip_suffix(')')).ok_or_else(invalid)?;
+            names.split(',').map(|name| name.trim().to_string()).collect()
+        };
+        let is_name = |name: &String| {
+            name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        };
+        if !params.iter().all(is_name) {
+            return Err(invalid());
+        }
+
+        let condition = match condition {
+            Some(condition) => Some(Parser::new(condition, &params).condition().map_err(reported)?),
+            None => None,
+        };
+        let successor = Parser::new(successor, &params).modules().map_err(reported)?;
+        Ok(Rule { predecessor: symbol, weight, params, condition, successor })
+    }
+}
+
+/// How modules are drawn, see [`LSystem::interpret()`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TurtleParams {
+    /// Degrees turned by `+` and `-` without a parameter
+    pub angle: f64,
+    /// Initial heading in degrees, 90 points up
+    pub heading: f64,
+    /// Distance of `F`, `G`, `f` and `g` without a parameter
+    pub step: f64,
+    /// Step multiplier of `"` without a parameter
+    pub length_scale: f64,
+    /// Initial line width in the units of `step`, 0 is a hairline
+    pub width: f64,
+    /// Width multiplier of `!` without a parameter
+    pub width_scale: f64,
+}
+
+impl Default for TurtleParams {
+    fn default() -> Self {
+        TurtleParams { angle: 90.0, heading: 90.0, step: 1.0, length_scale: 0.9, width: 0.0, width_scale: 0.7 }
+    }
+}
+
+/// Line drawn by the turtle, y points up
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Segment {
+    pub from: (f64, f64),
+    pub to: (f64, f64),
+    pub width: f64,
+    /// Drawn distance from the axiom to `from` along the branches, where growth reaches the segment
+    pub start: f64,
+}
+
+impl Segment {
+    pub fn length(&self) -> f64 {
+        (self.to.0 - self.from.0).hypot(self.to.1 - self.from.1)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TurtleState {
+    position: (f64, f64),
+    /// Radians
+    heading: f64,
+    step: f64,
+    width: f64,
+    distance: f64,
+}
+
+/// Lindenmayer system: the axiom is rewritten by the rules every generation, then drawn by a turtle.
+///
+/// Turtle symbols, the optional parameter replaces the default of [`TurtleParams`]:
+/// - `F(l)`, `G(l)` draw forward, `f(l)`, `g(l)` move without drawing
+/// - `+(a)`, `-(a)` turn left and right by degrees, `|` turns around
+/// - `[` and `]` save and restore the turtle, for branches
+/// - `!(w)` sets the line width, multiplies it by `width_scale` without a parameter
+/// - `"(s)` multiplies the step
+///
+/// Other symbols are only rewritten.
+///
+/// Example:
+/// ```
+/// let plant = LSystem::new("X")?
+///     .with_rule("X -> F+[[X]-X]-F[-FX]+X")?
+///     .with_rule("F -> FF")?
+///     .with_angle(25.0);
+/// let segments = plant.interpret(&plant.generate(6, &mut rng));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct LSystem {
+    pub turtle: TurtleParams,
+    axiom: Vec<Module>,
+    rules: Vec<Rule>,
+}
+
+impl LSystem {
+    /// `axiom` is a string of modules, parameters must be constant, e.g. `A(1, 0.1)`
+    pub fn new(axiom: &str) -> Result<Self, LSystemError> {
+        let invalid = || LSystemError::InvalidAxiom(axiom.to_string());
+        let modules = Parser::new(axiom, &[]).modules().map_err(|_| invalid())?;
+        let axiom = modules
+            .into_iter()
+            .map(|(symbol, args)| Module { symbol, params: args.iter().map(|arg| arg.eval(&[])).collect() })
+            .collect();
+        Ok(Self { turtle: TurtleParams::default(), axiom, rules: Vec::new() })
+    }
+
+    /// Parse and add a [`Rule`]
+    pub fn with_rule(mut self, rule: &str) -> Result<Self, LSystemError> {
+        self.rules.push(rule.parse()?);
+        Ok(self)
+    }
+
+    /// Turn of `+` and `-` in degrees
+    pub fn with_angle(mut self, angle: f64) -> Self {
+        self.turtle.angle = angle;
+        self
+    }
+
+    pub fn with_turtle(mut self, turtle: TurtleParams) -> Self {
+        self.turtle = turtle;
+        self
+    }
+
+    pub fn axiom(&self) -> &[Module] {
+        &self.axiom
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Koch snowflake, 4 generations
+    pub fn koch_snowflake() -> Self {
+        Self::preset("F--F--F", &["F -> F+F--F+F"]).with_angle(60.0)
+    }
+
+    /// Heighway dragon, 12 generations
+    pub fn dragon_curve() -> Self {
+        Self::preset("FX", &["X -> X+YF+", "Y -> -FX-Y"])
+    }
+
+    /// Sierpinski triangle drawn as one curve, 7 generations
+    pub fn sierpinski_arrowhead() -> Self {
+        let turtle = TurtleParams { angle: 60.0, heading: 0.0, ..TurtleParams::default() };
+        Self::preset("F", &["F -> G+F+G", "G -> F-G-F"]).with_turtle(turtle)
+    }
+
+    /// Hilbert curve, 6 generations
+    pub fn hilbert_curve() -> Self {
+        Self::preset("A", &["A -> +BF-AFA-FB+", "B -> -AF+BFB+FA-"])
+    }
+
+    /// Fractal plant from "The Algorithmic Beauty of Plants", 6 generations
+    pub fn fractal_plant() -> Self {
+        Self::preset("X", &["X -> F+[[X]-X]-F[-FX]+X", "F -> FF"]).with_angle(25.0)
+    }
+
+    /// Every branch picks one of three shapes, each generation looks different. 5 generations
+    pub fn stochastic_bush() -> Self {
+        let rules = ["F -(0.33)-> F[+F]F[-F]F", "F -(0.33)-> F[+F]F", "F -(0.34)-> F[-F]F"];
+        Self::preset("F", &rules).with_angle(22.0)
+    }
+
+    /// Tapering tree that stops branching below a length, 20 generations grow it fully
+    pub fn parametric_tree() -> Self {
+        let rules = [
+            "A(l, w) : l >= 0.05 -> !(w)F(l)[+(28)A(l * 0.72, w * 0.7)]-(8)A(l * 0.82, w * 0.75)",
+            "A(l, w) : l >= 0.05 && l < 0.3 -(0.5)-> !(w)F(l)[-(32)A(l * 0.7, w * 0.7)]+(10)A(l * 0.8, w * 0.75)",
+        ];
+        Self::preset("A(1, 0.12)", &rules)
+    }
+
+    fn preset(axiom: &str, rules: &[&str]) -> Self {
+        rules
+            .iter()
+            .try_fold(LSystem::new(axiom).expect("Preset is valid"), |system, rule| system.with_rule(rule))
+            .expect("Preset is valid")
+    }
+
+    /// Rewrite the axiom `generations` times, stops early when no rule applies or the next generation would be
+    /// too long. `rng` picks between stochastic rules
+    pub fn generate<R: Rng>(&self, generations: u32, rng: &mut R) -> Vec<Module> {
+        let mut modules = self.axiom.clone();
+        for _ in 0..generations {
+            let mut next = Vec::with_capacity(modules.len() * 2);
+            let mut rewritten = false;
+            for module in &modules {
+                // Weighted pick in one pass, the later rule replaces the choice with its share of the total
+                let mut total = 0.0;
+                let mut chosen = None;
+                for rule in self.rules.iter().filter(|rule| rule.weight > 0.0 && rule.matches(module)) {
+                    total += rule.weight;
+                    if chosen.is_none() || rng.gen_range(0.0..total) < rule.weight {
+                        chosen = Some(rule);
+                    }
+                }
+                match chosen {
+                    Some(rule) => {
+                        rule.apply(module, &mut next);
+                        rewritten = true;
+                    }
+                    None => next.push(module.clone()),
+                }
+                if next.len() > MAX_MODULES {
+                    return modules;
+                }
+            }
+
+            modules = next;
+            if !rewritten {
+                break;
+            }
+        }
+        modules
+    }
+
+    /// Walk the turtle over `modules`, starting at the origin
+    pub fn interpret(&self, modules: &[Module]) -> Vec<Segment> {
+        let turtle = &self.turtle;
+        let mut state = TurtleState {
+            position: (0.0, 0.0),
+            heading: turtle.heading.to_radians(),
+            step: turtle.step,
+            width: turtle.width,
+            distance: 0.0,
+        };
+        let mut saved = Vec::new();
+        let mut segments = Vec::new();
+
+        for module in modules {
+            let param = module.params.first().copied();
+            match module.symbol {
+                'F' | 'G' | 'f' | 'g' => {
+                    let length = param.unwrap_or(state.step);
+                    let from = state.position;
+                    state.position = (from.0 + length * state.heading.cos(), from.1 + length * state.heading.sin());
+                    if module.symbol.is_uppercase() {
+                        segments.push(Segment { from, to: state.position, width: state.width, start: state.distance });
+                        state.distance += length.abs();
+                    }
+                }
+                '+' => state.heading += param.unwrap_or(turtle.angle).to_radians(),
+                '-' => state.heading -= param.unwrap_or(turtle.angle).to_radians(),
+                '|' => state.heading += PI,
+                '[' => saved.push(state),
+                ']' => state = saved.pop().unwrap_or(state),
+                '!' => state.width = param.unwrap_or(state.width * turtle.width_scale),
+                '"' => state.step *= param.unwrap_or(turtle.length_scale),
+                _ => {}
+            }
+        }
+        segments
+    }
+}
+
+/// Grows an [`LSystem`] on screen: branches extend from the axiom at constant speed, so every branch starts
+/// when the one below it reaches the fork. Once grown it holds, then regrows, stochastic systems with a new shape.
+/// Shapes are generated in [`LSystemScene::update()`], nothing is drawn before the first update.
+///
+/// Example:
+/// ```
+/// let mut scene = LSystemScene::new(LSystem::stochastic_bush(), 5).with_seed(3);
+/// // every frame
+/// scene.update(delta_time);
+/// scene.draw(&mut canvas);
+/// ```
+#[derive(Clone, Debug)]
+pub struct LSystemScene {
+    /// Seconds until the whole system is drawn
+    pub growth_duration: f64,
+    /// Seconds the grown system stays before regrowing
+    pub hold: f64,
+    /// Colors from the axiom to the farthest tips
+    pub colors: Vec<RGB<u8>>,
+    /// Empty border around the drawing, relative to the surface size
+    pub margin: f64,
+    system: LSystem,
+    generations: u32,
+    rng: StdRng,
+    segments: Vec<Segment>,
+    /// Lower left and upper right corners of all segments
+    bounds: ((f64, f64), (f64, f64)),
+    /// Farthest end of a segment from the axiom
+    length: f64,
+    /// Seconds since the growth started
+    time: f64,
+    /// The next update generates a new shape
+    pending: bool,
+}
+
+impl LSystemScene {
+    pub fn new(system: LSystem, generations: u32) -> Self {
+        Self {
+            growth_duration: 20.0,
+            hold: 10.0,
+            colors: vec![RGB::new(92, 64, 38), RGB::new(70, 140, 60), RGB::new(180, 225, 110)],
+            margin: 0.05,
+            system,
+            generations,
+            rng: StdRng::seed_from_u64(rand::thread_rng().gen()),
+            segments: Vec::new(),
+            bounds: ((0.0, 0.0), (0.0, 0.0)),
+            length: 0.0,
+            time: 0.0,
+            pending: true,
+        }
+    }
+
+    /// Same seed gives the same sequence of shapes
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.pending = true;
+        self
+    }
+
+    pub fn with_colors(mut self, colors: Vec<RGB<u8>>) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    pub fn system(&self) -> &LSystem {
+        &self.system
+    }
+
+    /// Growth starts over with the next update
+    pub fn set_system(&mut self, system: LSystem, generations: u32) {
+        self.system = system;
+        self.generations = generations;
+        self.pending = true;
+    }
+
+    /// Empty until the first update
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Drawn part of the system in `[0, 1]`
+    pub fn progress(&self) -> f64 {
+        if self.growth_duration > 0.0 { (self.time / self.growth_duration).min(1.0) } else { 1.0 }
+    }
+
+    /// `delta_time` in seconds. Generates the first shape, and a new one once the grown one was held
+    pub fn update(&mut self, delta_time: f64) {
+        self.time += delta_time;
+        if self.pending || self.time >= self.growth_duration + self.hold {
+            self.regrow();
+        }
+    }
+
+    /// Generate the next shape and start growing it
+    pub fn regrow(&mut self) {
+        let modules = self.system.generate(self.generations, &mut self.rng);
+        self.segments = self.system.interpret(&modules);
+        self.time = 0.0;
+        self.pending = false;
+
+        let mut min = (f64::INFINITY, f64::INFINITY);
+        let mut max = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for point in self.segments.iter().flat_map(|segment| [segment.from, segment.to]) {
+            min = (min.0.min(point.0), min.1.min(point.1));
+            max = (max.0.max(point.0), max.1.max(point.1));
+        }
+        self.bounds = (min, max);
+        self.length = self.segments.iter().map(|segment| segment.start + segment.length()).fold(0.0, f64::max);
+    }
+
+    /// Fit the system into the surface, keeping its proportions. Canvas local coordinates must be pixels
+    /// of the surface (see [`Canvas::new()`])
+    pub fn draw<S: Surface>(&self, canvas: &mut Canvas<S>) {
+        if self.segments.is_empty() || self.length <= 0.0 {
+            return;
+        }
+
+        let (width, height) = (canvas.width() as f64, canvas.height() as f64);
+        let (min, max) = self.bounds;
+        let size = ((max.0 - min.0).max(f64::EPSILON), (max.1 - min.1).max(f64::EPSILON));
+        let scale = (width * (1.0 - 2.0 * self.margin) / size.0).min(height * (1.0 - 2.0 * self.margin) / size.1);
+
+        canvas.push();
+        canvas.translate(width / 2.0, height / 2.0);
+        canvas.scale(scale, -scale);
+        canvas.translate(-(min.0 + max.0) / 2.0, -(min.1 + max.1) / 2.0);
+
+        let reached = self.progress() * self.length;
+        for segment in self.segments.iter().filter(|segment| segment.start < reached) {
+            let length = segment.length();
+            let grown = if length > 0.0 { ((reached - segment.start) / length).min(1.0) } else { 1.0 };
+            let to = (
+                segment.from.0 + (segment.to.0 - segment.from.0) * grown,
+                segment.from.1 + (segment.to.1 - segment.from.1) * grown,
+            );
+            let color = interpolate_colors(&self.colors, (segment.start / self.length) as f32);
+
+            if segment.width * scale < HAIRLINE_WIDTH || length == 0.0 {
+                canvas.draw_line(segment.from, to, color);
+                continue;
+            }
+            // Thick branch as a quad, the circle rounds the joint with its parent
+            let half = segment.width / 2.0;
+            let normal = (
+                -(segment.to.1 - segment.from.1) / length * half,
+                (segment.to.0 - segment.from.0) / length * half,
+            );
+            canvas.fill_polygon(
+                &[
+                    (segment.from.0 + normal.0, segment.from.1 + normal.1),
+                    (to.0 + normal.0, to.1 + normal.1),
+                    (to.0 - normal.0, to.1 - normal.1),
+                    (segment.from.0 - normal.0, segment.from.1 - normal.1),
+                ],
+                color,
+            );
+            canvas.fill_circle(segment.from, half, color);
+        }
+
+        canvas.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(system: &LSystem, generations: u32, seed: u64) -> Vec<Module> {
+        system.generate(generations, &mut StdRng::seed_from_u64(seed))
+    }
+
+    fn module(symbol: char, params: &[f64]) -> Module {
+        Module { symbol, params: params.to_vec() }
+    }
+
+    #[test]
+    fn preset_segment_counts() {
+        for (system, generations, segments) in [
+            (LSystem::koch_snowflake(), 4, 768),
+            (LSystem::dragon_curve(), 12, 4096),
+            (LSystem::hilbert_curve(), 6, 4095),
+        ] {
+            assert_eq!(system.interpret(&generate(&system, generations, 0)).len(), segments);
+        }
+    }
+
+    #[test]
+    fn same_seed_same_shape() {
+        let system = LSystem::stochastic_bush();
+        assert_eq!(generate(&system, 5, 3), generate(&system, 5, 3));
+        assert_ne!(generate(&system, 5, 3), generate(&system, 5, 4));
+    }
+
+    #[test]
+    fn parse_rules() {
+        let rule: Rule = "F -(0.33)-> F[+F]F".parse().unwrap();
+        assert_eq!((rule.predecessor, rule.weight), ('F', 0.33));
+        let rule: Rule = "A(l) : l > 1 -(2) -> F".parse().unwrap();
+        assert_eq!(rule.weight, 1.0, "a weight apart from the arrow belongs to the condition");
+
+        let invalid = |rule: &str| LSystemError::InvalidRule(rule.to_string());
+        assert_eq!("F -(-1)-> F".parse::<Rule>(), Err(invalid("F -(-1)-> F")));
+        assert_eq!("F".parse::<Rule>(), Err(invalid("F")));
+        assert_eq!("A(l -> F".parse::<Rule>(), Err(invalid("A(l -> F")));
+        assert_eq!("F -> F(1e)".parse::<Rule>(), Err(invalid("F -> F(1e)")));
+        assert_eq!("A(l) -> B(x)".parse::<Rule>(), Err(LSystemError::UnknownParameter("x".to_string())));
+    }
+
+    #[test]
+    fn parameters_and_conditions() {
+        let system = LSystem::new("A(8)").unwrap().with_rule("A(l) : l > 1 -> F(l)A(l / 2)").unwrap();
+        let expected = [module('F', &[8.0]), module('F', &[4.0]), module('F', &[2.0]), module('A', &[1.0])];
+        assert_eq!(generate(&system, 10, 0), expected);
+
+        let system = LSystem::new("A(2, 3)").unwrap().with_rule("A(l, w) -> F(l ^ 2, -w, 1e-3, 2E+2)").unwrap();
+        assert_eq!(generate(&system, 1, 0), [module('F', &[4.0, -3.0, 0.001, 200.0])]);
+    }
+
+    #[test]
+    fn generations_stop_at_the_limit() {
+        let system = LSystem::new("F").unwrap().with_rule("F -> FF").unwrap();
+        assert_eq!(generate(&system, 30, 0).len(), 1 << 20);
+    }
+}
@@ -0,0 +1,150 @@
+use std::sync::Mutex;
+use std::thread;
+
+/// Rows per work item. Small bands balance the load, fractals are much slower inside the set than outside
+pub const BAND_ROWS: usize = 8;
+
+/// Number of hardware threads, at least 1
+pub fn thread_count() -> usize {
+    thread::available_parallelism().map_or(1, |count| count.get())
+}
+
+/// Fill `pixels` (<b>width * height</b>, row by row) with `pixel(x, y)` on all hardware threads.
+/// Bands of [`BAND_ROWS`] rows are handed out to the threads as they finish the previous ones
+pub fn render_rows<F>(width: usize, height: usize, pixels: &mut [u32], pixel: F)
+where
+    F: Fn(usize, usize) -> u32 + Sync,
+{
+    render_bands(width, height, pixels, thread_count(), |y, row| {
+        for (x, value) in row.iter_mut().enumerate() {
+            *value = pixel(x, y);
+        }
+    });
+}
+
+/// Like [`render_rows()`], but `row(y, pixels)` fills a whole row, and the number of `threads` is given.
+/// One thread renders on the calling thread without spawning
+pub fn render_bands<F>(width: usize, height: usize, pixels: &mut [u32], threads: usize, row: F)
+where
+    F: Fn(usize, &mut [u32]) + Sync,
+{
+    render_band_slices(width, height, pixels, threads, |top, band_pixels| {
+        for (i, row_pixels) in band_pixels.chunks_mut(width).enumerate() {
+            row(top + i, row_pixels);
+        }
+    });
+}
+
+/// Like [`render_bands()`], but `band(top, pixels)` fills up to [`BAND_ROWS`] whole rows starting at row `top`,
+/// e.g. to share a buffer between the rows of a band
+pub fn render_band_slices<F>(width: usize, height: usize, pixels: &mut [u32], threads: usize, band: F)
+where
+    F: Fn(usize, &mut [u32]) + Sync,
+{
+    assert!(pixels.len() >= width * height, "pixels must be width * height size");
+    if width == 0 || height == 0 {
+        return;
+    }
+    let pixels = &mut pixels[..width * height];
+
+    let render_band = |index: usize, band_pixels: &mut [u32]| band(index * BAND_ROWS, band_pixels);
+
+    if threads <= 1 {
+        for (index, band_pixels) in pixels.chunks_mut(width * BAND_ROWS).enumerate() {
+            render_band(index, band_pixels);
+        }
+        return;
+    }
+
+    let bands = Mutex::new(pixels.chunks_mut(width * BAND_ROWS).enumerate());
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let next = bands.lock().unwrap().next();
+                match next {
+                    Some((index, band_pixels)) => render_band(index, band_pixels),
+                    None => break,
+                }
+            });
+        }
+    });
+}
+
+/// Call `item(value)` for each of `items` on `threads` threads, items are handed out one at a time.
+/// One thread runs on the calling thread without spawning
+pub fn for_each<T, F>(items: &mut [T], threads: usize, item: F)
+where
+    T: Send,
+    F: Fn(&mut T) + Sync,
+{
+    if threads <= 1 || items.len() <= 1 {
+        items.iter_mut().for_each(item);
+        return;
+    }
+
+    let items = Mutex::new(items.iter_mut());
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let next = items.lock().unwrap().next();
+                match next {
+                    Some(value) => item(value),
+                    None => break,
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{calc_mandelbrot, calc_mandelbrot_parallel};
+    use super::*;
+
+    /// Heights below, at and around multiples of [`BAND_ROWS`]
+    const SIZES: [(usize, usize); 5] = [(17, 1), (33, BAND_ROWS), (40, 37), (64, 64), (5, 3 * BAND_ROWS + 1)];
+
+    #[test]
+    fn parallel_mandelbrot_matches_serial() {
+        for (width, height) in SIZES {
+            let mut serial = vec![0; width * height];
+            calc_mandelbrot(width, height, 200, &mut serial);
+            let mut parallel = vec![0; width * height];
+            calc_mandelbrot_parallel(width, height, 200, &mut parallel);
+            assert_eq!(serial, parallel, "{}x{}", width, height);
+        }
+    }
+
+    #[test]
+    fn bands_match_for_any_thread_count() {
+        let row = |y: usize, pixels: &mut [u32]| {
+            for (x, pixel) in pixels.iter_mut().enumerate() {
+                *pixel = (x as u32).wrapping_mul(2654435761) ^ (y as u32 + 1);
+            }
+        };
+        for (width, height) in SIZES {
+            let mut single = vec![0; width * height];
+            render_bands(width, height, &mut single, 1, row);
+            for threads in [2, 3, 8] {
+                let mut multi = vec![0; width * height];
+                render_bands(width, height, &mut multi, threads, row);
+                assert_eq!(single, multi, "{}x{} on {} threads", width, height, threads);
+            }
+        }
+    }
+
+    #[test]
+    fn rows_beyond_the_image_are_not_touched() {
+        let mut pixels = vec![7; 10 * 5 + 3];
+        render_rows(10, 5, &mut pixels, |x, y| (y * 10 + x) as u32);
+        assert_eq!(pixels[..50], (0..50).collect::<Vec<u32>>());
+        assert_eq!(pixels[50..], [7, 7, 7]);
+    }
+
+    #[test]
+    fn for_each_visits_every_item_once() {
+        let mut items: Vec<u32> = (0..100).collect();
+        for_each(&mut items, 4, |item| *item *= 2);
+        assert_eq!(items, (0..100).map(|i| i * 2).collect::<Vec<u32>>());
+    }
+}
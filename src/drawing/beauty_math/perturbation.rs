@@ -0,0 +1,126 @@
+use super::coloring::{Orbit, OrbitTrap, BAILOUT};
+use super::double_double::DoubleDouble;
+
+/// Orbit of the reference point iterated in [`DoubleDouble`], other pixels only iterate the small
+/// difference from it in `f64`:
+///
+/// `δ(n+1) = 2 Z(n) δ(n) + δ(n)² + δc`
+///
+/// When `|Z + δ|` gets smaller than `|δ|` the pixel is rebased onto the start of the reference orbit,
+/// which removes glitches without extra reference points (Zhuoran, 2021)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReferenceOrbit {
+    center: (DoubleDouble, DoubleDouble),
+    /// `Z(0) = 0, Z(1), ...` rounded to `f64`, stops after the escape or at `max_iter`, at least `Z(1)`
+    points: Vec<(f64, f64)>,
+}
+
+impl ReferenceOrbit {
+    /// At least one iteration even for `max_iter` 0, [`Self::orbit()`] steps along the reference
+    pub fn new(center: (DoubleDouble, DoubleDouble), max_iter: u32) -> Self {
+        let (mut x, mut y) = (DoubleDouble::ZERO, DoubleDouble::ZERO);
+        let max_iter = max_iter.max(1);
+        let mut points = Vec::with_capacity(max_iter as usize + 1);
+        points.push((0.0, 0.0));
+
+        for _ in 0..max_iter {
+            let x_temp = x.sqr() - y.sqr() + center.0;
+            y = (x * y).mul_f64(2.0) + center.1;
+            x = x_temp;
+
+            let point = (x.to_f64(), y.to_f64());
+            points.push(point);
+            if point.0 * point.0 + point.1 * point.1 > BAILOUT * BAILOUT {
+                break;
+            }
+        }
+
+        Self { center, points }
+    }
+
+    pub fn center(&self) -> (DoubleDouble, DoubleDouble) {
+        self.center
+    }
+
+    /// Iterations of the reference orbit itself
+    pub fn len(&self) -> usize {
+        self.points.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Orbit of the point `center + delta_c`, same result as [`super::coloring::mandelbrot_orbit()`]
+    /// with precision of the reference
+    #[inline(always)]
+    pub fn orbit(&self, delta_c: (f64, f64), max_iter: u32, trap: Option<&OrbitTrap>) -> Orbit {
+        let points = &self.points;
+        let (mut dx, mut dy) = (0.0f64, 0.0f64);
+        let (mut derivative_x, mut derivative_y) = (0.0f64, 0.0f64);
+        let (mut x, mut y) = (0.0f64, 0.0f64);
+        let mut trap_distance = f64::INFINITY;
+        let mut reference = 0;
+        let mut i = 0;
+
+        while i < max_iter {
+            // Derivative of the full value: dz = 2 z dz + 1
+            let derivative_temp = 2.0 * (x * derivative_x - y * derivative_y) + 1.0;
+            derivative_y = 2.0 * (x * derivative_y + y * derivative_x);
+            derivative_x = derivative_temp;
+
+            let (zx, zy) = points[reference];
+            let dx_temp = 2.0 * (zx * dx - zy * dy) + dx * dx - dy * dy + delta_c.0;
+            dy = 2.0 * (zx * dy + zy * dx) + 2.0 * dx * dy + delta_c.1;
+            dx = dx_temp;
+            reference += 1;
+            i += 1;
+
+            let (zx, zy) = points[reference];
+            x = zx + dx;
+            y = zy + dy;
+
+            if let Some(trap) = trap {
+                trap_distance = trap_distance.min(trap.distance((x, y)));
+            }
+            let modulus = x * x + y * y;
+            if modulus > BAILOUT * BAILOUT {
+                break;
+            }
+            if modulus < dx * dx + dy * dy || reference == points.len() - 1 {
+                (dx, dy) = (x, y);
+                reference = 0;
+            }
+        }
+
+        Orbit {
+            iterations: i,
+            escaped: i < max_iter,
+            z: (x, y),
+            derivative: (derivative_x, derivative_y),
+            trap_distance,
+            degree: 2.0,
+            basin: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::coloring::mandelbrot_orbit;
+    use super::*;
+
+    #[test]
+    fn short_references() {
+        let center = (DoubleDouble::from(-0.5), DoubleDouble::from(0.5));
+        for reference_iter in [0, 1, 3] {
+            let reference = ReferenceOrbit::new(center, reference_iter);
+            assert!(!reference.is_empty());
+            for delta_c in [(0.0, 0.0), (0.01, -0.02), (1.5, 0.0)] {
+                let c = (-0.5 + delta_c.0, 0.5 + delta_c.1);
+                let iterations = reference.orbit(delta_c, 100, None).iterations;
+                assert_eq!(iterations, mandelbrot_orbit(c, 100, None).iterations, "{:?}", delta_c);
+            }
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::super::framebuffer::Framebuffer;
+use super::super::transform::Transform;
+use super::coloring::FractalColors;
+use super::escape_time::EscapeTime;
+use super::parallel;
+use super::view::FractalView;
+
+/// Side of a cached tile in pixels
+pub const TILE_SIZE: usize = 64;
+
+/// Block sizes of the refinement passes. Every pass computes one pixel per block and fills the block with it
+const PASSES: [usize; 4] = [8, 4, 2, 1];
+
+/// Cached tiles by default, about 5 screens of 1920x1080
+const DEFAULT_CAPACITY: usize = 2500;
+
+/// Farthest the view centre may pan from the anchor in pixels, tile positions stay exact far below it
+const MAX_ANCHOR_OFFSET: f64 = 1e9;
+
+/// Stops a render in progress, e.g. from the window thread when the view changes. Clones share the flag
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RenderProgress {
+    /// Every visible pixel is computed
+    Complete,
+    /// Time ran out, some tiles are coarse or missing. Call again to continue
+    Partial,
+    /// Stopped by [`CancelToken::cancel()`]
+    Cancelled,
+}
+
+/// Tiles lie on a grid of the complex plane, so they are reused while the view only pans.
+/// `x` and `y` count tiles from the [`Anchor`]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+struct TileKey {
+    max_iter: u32,
+    x: i64,
+    y: i64,
+}
+
+/// Origin of the tile grid, a point of the complex plane near the view centre. Pixel positions relative
+/// to it stay exact at any zoom, absolute ones do not fit in `f64` or `i64` at deep zooms
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Anchor {
+    zoom: u64,
+    rotation: u64,
+    point: (f64, f64),
+}
+
+#[derive(Clone, Debug)]
+struct Tile {
+    key: TileKey,
+    pixels: Vec<u32>,
+    /// Refinement passes done, [`PASSES`] length when finished
+    passes: usize,
+    last_used: u64,
+}
+
+/// Renders an [`EscapeTime`] fractal in small time slices so painting never blocks.
+///
+/// The image is split into tiles of [`TILE_SIZE`]. Every tile is first rendered in 8x8 blocks, then refined
+/// to 4x4, 2x2 and single pixels, all visible tiles get a pass before any of them gets the next one.
+/// Finished and partial tiles are cached by iteration limit, panning only renders the new edge. Changing the zoom
+/// or the rotation, or panning very far, drops the cache.
+///
+/// Example:
+/// ```
+/// let mut renderer = ProgressiveRenderer::new(Mandelbrot, colors, 500);
+/// // every frame, continues where the last call stopped
+/// renderer.render(&view, Duration::from_millis(12), &mut framebuffer);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ProgressiveRenderer<F: EscapeTime> {
+    fractal: F,
+    colors: FractalColors,
+    max_iter: u32,
+    tiles: HashMap<TileKey, Tile>,
+    anchor: Option<Anchor>,
+    /// Cached tiles, least recently used ones are dropped beyond it
+    capacity: usize,
+    frame: u64,
+    cancel: CancelToken,
+}
+
+impl<F: EscapeTime> ProgressiveRenderer<F> {
+    pub fn new(fractal: F, colors: FractalColors, max_iter: u32) -> Self {
+        Self {
+            fractal,
+            colors,
+            max_iter,
+            tiles: HashMap::new(),
+            anchor: None,
+            capacity: DEFAULT_CAPACITY,
+            frame: 0,
+            cancel: CancelToken::default(),
+        }
+    }
+
+    /// Maximum number of cached tiles, at least 1
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    pub fn fractal(&self) -> &F {
+        &self.fractal
+    }
+
+    pub fn colors(&self) -> &FractalColors {
+        &self.colors
+    }
+
+    pub fn max_iter(&self) -> u32 {
+        self.max_iter
+    }
+
+    /// Drops the cache, tiles of other formulas are useless
+    pub fn set_fractal(&mut self, fractal: F) {
+        self.fractal = fractal;
+        self.clear();
+    }
+
+    /// Drops the cache, tiles keep finished pixels, not orbits
+    pub fn set_colors(&mut self, colors: FractalColors) {
+        self.colors = colors;
+        self.clear();
+    }
+
+    /// Tiles of the old limit stay cached until evicted
+    pub fn set_max_iter(&mut self, max_iter: u32) {
+        self.max_iter = max_iter;
+    }
+
+    /// Token that stops [`Self::render()`] from another thread
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+    }
+
+    /// Continue rendering `view` into `target` for at most about `budget`. Tiles that have no pass yet
+    /// keep the previous content of `target`. Pixels are snapped to the tile grid, the image may shift
+    /// by up to half a pixel against [`FractalView::to_complex()`]
+    pub fn render(&mut self, view: &FractalView, budget: Duration, target: &mut Framebuffer) -> RenderProgress {
+        let deadline = Instant::now() + budget;
+        self.cancel.reset();
+        self.frame += 1;
+
+        let (width, height) = (target.width(), target.height());
+        if width == 0 || height == 0 {
+            return RenderProgress::Complete;
+        }
+
+        // World pixels: the complex plane around the anchor divided into pixels, rotated with the view, y pointing down
+        let pixel_size = view.pixel_size(width, height);
+        // Inverse of the pixel transform built directly, its determinant underflows any threshold at deep zooms
+        let to_world =
+            Transform::rotation(-view.rotation).then(&Transform::scaling(1.0 / pixel_size, -1.0 / pixel_size));
+        let anchor = self.move_anchor(view, &to_world);
+        let to_complex = Transform::scaling(pixel_size, -pixel_size)
+            .then(&Transform::rotation(view.rotation))
+            .then(&Transform::translation(anchor.0, anchor.1));
+        let center = to_world.apply((view.center.0 - anchor.0, view.center.1 - anchor.1));
+        let origin = ((center.0 - width as f64 / 2.0).round() as i64, (center.1 - height as f64 / 2.0).round() as i64);
+
+        let mut visible = self.take_visible(origin, width, height);
+        let progress = self.refine(&mut visible, &to_complex, pixel_size, deadline);
+
+        for tile in &visible {
+            if tile.passes > 0 {
+                blit_tile(tile, origin, target);
+            }
+        }
+        self.tiles.extend(visible.into_iter().map(|tile| (tile.key, tile)));
+        self.evict();
+        progress
+    }
+
+    /// Anchor of the tile grid for `view`. A new one at the view centre drops the cache, it is needed when the zoom
+    /// or the rotation changed or the view is too far from the old one
+    fn move_anchor(&mut self, view: &FractalView, to_world: &Transform) -> (f64, f64) {
+        let (zoom, rotation) = (view.zoom.to_bits(), view.rotation.to_bits());
+        if let Some(anchor) = self.anchor.filter(|anchor| anchor.zoom == zoom && anchor.rotation == rotation) {
+            let offset = to_world.apply((view.center.0 - anchor.point.0, view.center.1 - anchor.point.1));
+            if offset.0.abs() < MAX_ANCHOR_OFFSET && offset.1.abs() < MAX_ANCHOR_OFFSET {
+                return anchor.point;
+            }
+        }
+
+        self.tiles.clear();
+        self.anchor = Some(Anchor { zoom, rotation, point: view.center });
+        view.center
+    }
+
+    /// Remove the tiles covering the screen from the cache (or create them), nearest to the centre first
+    fn take_visible(&mut self, origin: (i64, i64), width: usize, height: usize) -> Vec<Tile> {
+        let size = TILE_SIZE as i64;
+        let (left, top) = (origin.0.div_euclid(size), origin.1.div_euclid(size));
+        let right = (origin.0 + width as i64 - 1).div_euclid(size);
+        let bottom = (origin.1 + height as i64 - 1).div_euclid(size);
+        let middle = ((left + right) as f64 / 2.0, (top + bottom) as f64 / 2.0);
+
+        let mut visible = Vec::new();
+        for y in top..=bottom {
+            for x in left..=right {
+                let key = TileKey { max_iter: self.max_iter, x, y };
+                let mut tile = self.tiles.remove(&key).unwrap_or_else(|| Tile {
+                    key,
+                    pixels: vec![0; TILE_SIZE * TILE_SIZE],
+                    passes: 0,
+                    last_used: 0,
+                });
+                tile.last_used = self.frame;
+                visible.push(tile);
+            }
+        }
+
+        let distance = |tile: &Tile| (tile.key.x as f64 - middle.0).hypot(tile.key.y as f64 - middle.1);
+        visible.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        visible
+    }
+
+    /// Run refinement passes over `tiles` until they are finished, the deadline passes or the render is cancelled
+    fn refine(&self, tiles: &mut [Tile], to_complex: &Transform, pixel_size: f64, deadline: Instant) -> RenderProgress {
+        let threads = parallel::thread_count();
+        for pass in 0..PASSES.len() {
+            parallel::for_each(tiles, threads, |tile| {
+                if tile.passes == pass && !self.cancel.is_cancelled() && Instant::now() < deadline {
+                    self.render_pass(tile, to_complex, pixel_size);
+                }
+            });
+
+            if self.cancel.is_cancelled() {
+                return RenderProgress::Cancelled;
+            }
+            if Instant::now() >= deadline {
+                break;
+            }
+        }
+
+        if tiles.iter().all(|tile| tile.passes == PASSES.len()) {
+            RenderProgress::Complete
+        } else {
+            RenderProgress::Partial
+        }
+    }
+
+    /// Next refinement pass of `tile`, pixels already computed by coarser passes are skipped
+    fn render_pass(&self, tile: &mut Tile, to_complex: &Transform, pixel_size: f64) {
+        let block = PASSES[tile.passes];
+        let coarser = if tile.passes > 0 { PASSES[tile.passes - 1] } else { usize::MAX };
+        let (left, top) = (tile.key.x * TILE_SIZE as i64, tile.key.y * TILE_SIZE as i64);
+        let trap = self.colors.trap();
+
+        for y in (0..TILE_SIZE).step_by(block) {
+            for x in (0..TILE_SIZE).step_by(block) {
+                if tile.passes > 0 && x % coarser == 0 && y % coarser == 0 {
+                    continue;
+                }
+                let point = to_complex.apply(((left + x as i64) as f64 + 0.5, (top + y as i64) as f64 + 0.5));
+                let orbit = self.fractal.orbit(point, self.max_iter, trap);
+                let pixel = self.colors.pixel(&orbit, self.max_iter, pixel_size);
+                for row in tile.pixels[y * TILE_SIZE..(y + block) * TILE_SIZE].chunks_mut(TILE_SIZE) {
+                    row[x..x + block].fill(pixel);
+                }
+            }
+        }
+        tile.passes += 1;
+    }
+
+    /// Drop least recently used tiles beyond the capacity
+    fn evict(&mut self) {
+        if self.tiles.len() <= self.capacity {
+            return;
+        }
+        let mut ages: Vec<u64> = self.tiles.values().map(|tile| tile.last_used).collect();
+        ages.sort_unstable();
+        let oldest_kept = ages[ages.len() - self.capacity];
+        self.tiles.retain(|_, tile| tile.last_used >= oldest_kept);
+    }
+}
+
+/// Copy the part of `tile` that overlaps `target`, whose top left corner is the world pixel `origin`
+fn blit_tile(tile: &Tile, origin: (i64, i64), target: &mut Framebuffer) {
+    let (width, height) = (target.width() as i64, target.height() as i64);
+    let left = tile.key.x * TILE_SIZE as i64 - origin.0;
+    let top = tile.key.y * TILE_SIZE as i64 - origin.1;
+    let (from_x, to_x) = (left.max(0), (left + TILE_SIZE as i64).min(width));
+    let (from_y, to_y) = (top.max(0), (top + TILE_SIZE as i64).min(height));
+    if from_x >= to_x || from_y >= to_y {
+        return;
+    }
+
+    let pixels = target.pixels_mut();
+    for y in from_y..to_y {
+        let source = (y - top) as usize * TILE_SIZE;
+        let row = &tile.pixels[source + (from_x - left) as usize..source + (to_x - left) as usize];
+        let destination = (y * width) as usize;
+        pixels[destination + from_x as usize..destination + to_x as usize].copy_from_slice(row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::colors::colormap::Colormap;
+    use super::super::super::colors::RGB;
+    use super::super::coloring::Coloring;
+    use super::super::escape_time::Mandelbrot;
+    use super::*;
+
+    fn colors() -> FractalColors {
+        FractalColors::from_colormap(Coloring::Smooth, Colormap::Viridis).with_inside(RGB::new(1, 2, 3))
+    }
+
+    #[test]
+    fn every_pixel_of_a_tile_is_rendered() {
+        // Inside the main cardioid, every pixel gets the inside color
+        let view = FractalView::new((-0.1, 0.0), 50.0);
+        let mut renderer = ProgressiveRenderer::new(Mandelbrot, colors(), 100);
+        let mut target = Framebuffer::new(2 * TILE_SIZE + 5, TILE_SIZE + 3);
+        let progress = renderer.render(&view, Duration::from_secs(60), &mut target);
+
+        assert_eq!(progress, RenderProgress::Complete);
+        assert!(target.pixels().iter().all(|&pixel| pixel == 0x010203));
+    }
+
+    #[test]
+    fn first_pass_covers_the_whole_tile() {
+        let view = FractalView::new((-0.1, 0.0), 50.0);
+        let renderer = ProgressiveRenderer::new(Mandelbrot, colors(), 100);
+        let key = TileKey { max_iter: 100, x: 0, y: 0 };
+        let mut tile = Tile { key, pixels: vec![0; TILE_SIZE * TILE_SIZE], passes: 0, last_used: 0 };
+        let pixel_size = view.pixel_size(TILE_SIZE, TILE_SIZE);
+        let to_complex = Transform::scaling(pixel_size, -pixel_size).then(&Transform::translation(-0.1, 0.0));
+
+        renderer.render_pass(&mut tile, &to_complex, pixel_size);
+        assert!(tile.pixels.iter().all(|&pixel| pixel == 0x010203));
+    }
+
+    #[test]
+    fn deep_zoom_keeps_the_centre() {
+        // The determinant of the pixel transform underflows to zero at this zoom
+        let view = FractalView::new((1.0, 0.0), 1e170);
+        let mut renderer = ProgressiveRenderer::new(Mandelbrot, colors(), 100);
+        let mut target = Framebuffer::new(96, 64);
+        let progress = renderer.render(&view, Duration::from_secs(60), &mut target);
+
+        assert_eq!(progress, RenderProgress::Complete);
+        assert!(target.pixels().iter().all(|&pixel| pixel != 0x010203));
+    }
+
+    #[test]
+    fn panning_keeps_tiles_of_the_anchor() {
+        let mut renderer = ProgressiveRenderer::new(Mandelbrot, colors(), 50);
+        let mut target = Framebuffer::new(TILE_SIZE, TILE_SIZE);
+        renderer.render(&FractalView::new((-0.5, 0.0), 1e15), Duration::from_secs(60), &mut target);
+        let anchor = renderer.anchor;
+
+        // A few screens to the right at the same zoom keeps the grid, a new zoom starts a new one
+        renderer.render(&FractalView::new((-0.5 + 1e-14, 0.0), 1e15), Duration::from_secs(60), &mut target);
+        assert_eq!(renderer.anchor, anchor);
+        renderer.render(&FractalView::new((-0.5, 0.0), 2e15), Duration::from_secs(60), &mut target);
+        assert_ne!(renderer.anchor, anchor);
+    }
+
+    #[test]
+    fn zero_capacity_keeps_the_visible_tiles() {
+        let view = FractalView::new((-0.1, 0.0), 50.0);
+        let mut renderer = ProgressiveRenderer::new(Mandelbrot, colors(), 100).with_capacity(0);
+        let mut target = Framebuffer::new(2 * TILE_SIZE, TILE_SIZE);
+        renderer.render(&view, Duration::from_secs(60), &mut target);
+        renderer.render(&view, Duration::from_secs(60), &mut target);
+
+        // Tiles of the last frame are never evicted
+        assert!(!renderer.tiles.is_empty());
+        assert!(target.pixels().iter().all(|&pixel| pixel == 0x010203));
+    }
+}
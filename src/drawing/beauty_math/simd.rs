@@ -0,0 +1,241 @@
+#[cfg(target_arch = "x86")]
+use std::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+use std::time::{Duration, Instant};
+
+use super::mandelbrot;
+
+/// Instruction set for the vectorized Mandelbrot iteration. All levels give bit-identical results,
+/// the operations are the same as in the scalar loop, only several points run at once
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SimdLevel {
+    Scalar,
+    /// Two vectors of 2 `f64`, 4 points at once
+    Sse2,
+    /// Two vectors of 4 `f64`, 8 points at once
+    Avx2,
+}
+
+impl SimdLevel {
+    pub const ALL: [SimdLevel; 3] = [SimdLevel::Scalar, SimdLevel::Sse2, SimdLevel::Avx2];
+
+    /// The fastest level the CPU supports
+    pub fn detect() -> SimdLevel {
+        if SimdLevel::Avx2.is_supported() {
+            SimdLevel::Avx2
+        } else if SimdLevel::Sse2.is_supported() {
+            SimdLevel::Sse2
+        } else {
+            SimdLevel::Scalar
+        }
+    }
+
+    pub fn is_supported(self) -> bool {
+        match self {
+            SimdLevel::Scalar => true,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            SimdLevel::Sse2 => is_x86_feature_detected!("sse2"),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            SimdLevel::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+            _ => false,
+        }
+    }
+
+    /// Points iterated together
+    pub fn lanes(self) -> usize {
+        match self {
+            SimdLevel::Scalar => 1,
+            SimdLevel::Sse2 => 4,
+            SimdLevel::Avx2 => 8,
+        }
+    }
+}
+
+/// Mandelbrot iteration counts of the points `(cx[i], cy[i])` with the best supported [`SimdLevel`].
+/// Same results as iterating them one by one
+pub fn mandelbrot_points(cx: &[f64], cy: &[f64], max_iter: u32, iterations: &mut [u32]) {
+    mandelbrot_points_with(SimdLevel::detect(), cx, cy, max_iter, iterations);
+}
+
+/// Like [`mandelbrot_points()`] with the given `level`, unsupported levels fall back to the scalar loop
+pub fn mandelbrot_points_with(level: SimdLevel, cx: &[f64], cy: &[f64], max_iter: u32, iterations: &mut [u32]) {
+    assert!(cx.len() == cy.len() && cx.len() == iterations.len(), "cx, cy and iterations must have the same length");
+    if !level.is_supported() {
+        return mandelbrot_points_with(SimdLevel::Scalar, cx, cy, max_iter, iterations);
+    }
+
+    // Whole vectors first, the rest one by one
+    let vectorized = iterations.len() / level.lanes() * level.lanes();
+    match level {
+        SimdLevel::Scalar => {}
+        // Safety: support is checked above
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        SimdLevel::Sse2 => unsafe { mandelbrot_sse2(&cx[..vectorized], &cy[..vectorized], max_iter, iterations) },
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        SimdLevel::Avx2 => unsafe { mandelbrot_avx2(&cx[..vectorized], &cy[..vectorized], max_iter, iterations) },
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+        _ => unreachable!(),
+    }
+
+    let rest = if level == SimdLevel::Scalar { 0 } else { vectorized };
+    for ((value, &cx), &cy) in iterations[rest..].iter_mut().zip(&cx[rest..]).zip(&cy[rest..]) {
+        *value = mandelbrot(cx, cy, max_iter);
+    }
+}
+
+/// Counters are kept in `f64` lanes, exact far beyond any `max_iter`.
+/// `alive` lanes never come back once they escaped, so the counts match the scalar loop that stops there
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "sse2")]
+unsafe fn mandelbrot_sse2(cx: &[f64], cy: &[f64], max_iter: u32, iterations: &mut [u32]) {
+    let (two, four, one) = (_mm_set1_pd(2.0), _mm_set1_pd(4.0), _mm_set1_pd(1.0));
+
+    for (index, (cx, cy)) in cx.chunks_exact(4).zip(cy.chunks_exact(4)).enumerate() {
+        let c_re = [_mm_loadu_pd(cx.as_ptr()), _mm_loadu_pd(cx.as_ptr().add(2))];
+        let c_im = [_mm_loadu_pd(cy.as_ptr()), _mm_loadu_pd(cy.as_ptr().add(2))];
+        let mut x = [_mm_setzero_pd(); 2];
+        let mut y = [_mm_setzero_pd(); 2];
+        let mut count = [_mm_setzero_pd(); 2];
+        let mut alive = [_mm_cmpeq_pd(one, one); 2];
+
+        for _ in 0..max_iter {
+            let mut any = 0;
+            // Two independent vectors hide the latency of the multiplications
+            for k in 0..2 {
+                let xx = _mm_mul_pd(x[k], x[k]);
+                let yy = _mm_mul_pd(y[k], y[k]);
+                alive[k] = _mm_and_pd(alive[k], _mm_cmple_pd(_mm_add_pd(xx, yy), four));
+                any |= _mm_movemask_pd(alive[k]);
+                count[k] = _mm_add_pd(count[k], _mm_and_pd(alive[k], one));
+
+                let x_temp = _mm_add_pd(_mm_sub_pd(xx, yy), c_re[k]);
+                y[k] = _mm_add_pd(_mm_mul_pd(_mm_mul_pd(two, x[k]), y[k]), c_im[k]);
+                x[k] = x_temp;
+            }
+            if any == 0 {
+                break;
+            }
+        }
+
+        let mut counts = [0.0; 4];
+        _mm_storeu_pd(counts.as_mut_ptr(), count[0]);
+        _mm_storeu_pd(counts.as_mut_ptr().add(2), count[1]);
+        for (i, value) in counts.into_iter().enumerate() {
+            iterations[index * 4 + i] = value as u32;
+        }
+    }
+}
+
+/// Same as [`mandelbrot_sse2()`] with 4 lanes per vector
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "avx2")]
+unsafe fn mandelbrot_avx2(cx: &[f64], cy: &[f64], max_iter: u32, iterations: &mut [u32]) {
+    let (two, four, one) = (_mm256_set1_pd(2.0), _mm256_set1_pd(4.0), _mm256_set1_pd(1.0));
+
+    for (index, (cx, cy)) in cx.chunks_exact(8).zip(cy.chunks_exact(8)).enumerate() {
+        let c_re = [_mm256_loadu_pd(cx.as_ptr()), _mm256_loadu_pd(cx.as_ptr().add(4))];
+        let c_im = [_mm256_loadu_pd(cy.as_ptr()), _mm256_loadu_pd(cy.as_ptr().add(4))];
+        let mut x = [_mm256_setzero_pd(); 2];
+        let mut y = [_mm256_setzero_pd(); 2];
+        let mut count = [_mm256_setzero_pd(); 2];
+        let mut alive = [_mm256_cmp_pd::<_CMP_EQ_OQ>(one, one); 2];
+
+        for _ in 0..max_iter {
+            let mut any = 0;
+            for k in 0..2 {
+                let xx = _mm256_mul_pd(x[k], x[k]);
+                let yy = _mm256_mul_pd(y[k], y[k]);
+                alive[k] = _mm256_and_pd(alive[k], _mm256_cmp_pd::<_CMP_LE_OQ>(_mm256_add_pd(xx, yy), four));
+                any |= _mm256_movemask_pd(alive[k]);
+                count[k] = _mm256_add_pd(count[k], _mm256_and_pd(alive[k], one));
+
+                let x_temp = _mm256_add_pd(_mm256_sub_pd(xx, yy), c_re[k]);
+                y[k] = _mm256_add_pd(_mm256_mul_pd(_mm256_mul_pd(two, x[k]), y[k]), c_im[k]);
+                x[k] = x_temp;
+            }
+            if any == 0 {
+                break;
+            }
+        }
+
+        let mut counts = [0.0; 8];
+        _mm256_storeu_pd(counts.as_mut_ptr(), count[0]);
+        _mm256_storeu_pd(counts.as_mut_ptr().add(4), count[1]);
+        for (i, value) in counts.into_iter().enumerate() {
+            iterations[index * 8 + i] = value as u32;
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BenchmarkResult {
+    pub level: SimdLevel,
+    /// One frame on a single thread
+    pub duration: Duration,
+    /// Every iteration count is the same as with [`SimdLevel::Scalar`]
+    pub matches_scalar: bool,
+}
+
+/// Time [`super::calc_mandelbrot()`] area (`[-2, 2]` on both axes) on every supported level, single threaded.
+/// E.g. `benchmark(1920, 1080, 500)` to pick `max_iter` for the wallpaper or to check a new CPU
+pub fn benchmark(width: usize, height: usize, max_iter: u32) -> Vec<BenchmarkResult> {
+    let mut cx = Vec::with_capacity(width * height);
+    let mut cy = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            cx.push((x as f64 - width as f64 / 2.0) * 4.0 / width as f64);
+            cy.push((y as f64 - height as f64 / 2.0) * 4.0 / height as f64);
+        }
+    }
+
+    let mut scalar = vec![0; width * height];
+    let mut iterations = vec![0; width * height];
+    let mut results = Vec::new();
+    for level in SimdLevel::ALL.into_iter().filter(|level| level.is_supported()) {
+        let start = Instant::now();
+        mandelbrot_points_with(level, &cx, &cy, max_iter, &mut iterations);
+        let duration = start.elapsed();
+
+        if level == SimdLevel::Scalar {
+            scalar.copy_from_slice(&iterations);
+        }
+        results.push(BenchmarkResult { level, duration, matches_scalar: iterations == scalar });
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_level_matches_the_scalar_loop() {
+        // Points on and around the boundary, where the escape decision is the closest
+        let mut points = vec![(-2.0, 0.0), (0.25, 0.0), (-0.75, 0.0), (0.0, 1.0), (0.0, -1.0), (2.0, 0.0)];
+        for i in 0..50 {
+            let angle = i as f64 * std::f64::consts::TAU / 50.0;
+            // Main cardioid boundary
+            let (re, im) = (angle.cos() / 2.0, angle.sin() / 2.0);
+            points.push((re - (re * re - im * im) / 2.0, im - re * im));
+            points.push((0.5 * angle.cos() - 0.25 * angle.cos() * angle.cos(), 0.001 * i as f64));
+        }
+
+        for level in SimdLevel::ALL {
+            for len in [0, 1, 3, 5, 7, 9, 13, 31, points.len()] {
+                let (cx, cy): (Vec<f64>, Vec<f64>) = points[..len].iter().copied().unzip();
+                let mut iterations = vec![u32::MAX; len];
+                mandelbrot_points_with(level, &cx, &cy, 300, &mut iterations);
+
+                let expected: Vec<u32> = points[..len].iter().map(|&(cx, cy)| mandelbrot(cx, cy, 300)).collect();
+                assert_eq!(iterations, expected, "{:?} with {} points", level, len);
+            }
+        }
+    }
+
+    #[test]
+    fn benchmark_levels_match() {
+        assert!(benchmark(37, 11, 100).iter().all(|result| result.matches_scalar));
+    }
+}
@@ -0,0 +1,113 @@
+use super::super::transform::Transform;
+
+/// Visible part of the complex plane. The shorter side of the image spans `4 / zoom` units,
+/// so the picture keeps its proportions on any monitor. Imaginary axis points up.
+///
+/// Example:
+/// ```
+/// let mut view = FractalView::new((-0.745, 0.113), 200.0);
+/// view.zoom_at((mouse_x, mouse_y), 2.0, width, height);
+/// let c = view.pixel_to_complex((x as f64 + 0.5, y as f64 + 0.5), width, height);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FractalView {
+    /// Complex number at the centre of the image, (re, im)
+    pub center: (f64, f64),
+    /// 1 shows `[-2, 2]` on the shorter side
+    pub zoom: f64,
+    /// Radians, positive turns the picture clockwise
+    pub rotation: f64,
+}
+
+impl Default for FractalView {
+    fn default() -> Self {
+        Self { center: (0.0, 0.0), zoom: 1.0, rotation: 0.0 }
+    }
+}
+
+impl FractalView {
+    pub const fn new(center: (f64, f64), zoom: f64) -> Self {
+        Self { center, zoom, rotation: 0.0 }
+    }
+
+    pub fn with_rotation(mut self, rotation: f64) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Complex plane units per pixel
+    pub fn pixel_size(&self, width: usize, height: usize) -> f64 {
+        4.0 / (self.zoom * width.min(height).max(1) as f64)
+    }
+
+    /// Maps pixel coordinates (`(0, 0)` is the top left corner of the image, pixel centres are at `x + 0.5`)
+    /// to the complex plane
+    pub fn to_complex(&self, width: usize, height: usize) -> Transform {
+        let scale = self.pixel_size(width, height);
+        Transform::translation(-(width as f64) / 2.0, -(height as f64) / 2.0)
+            .then(&Transform::scaling(scale, -scale))
+            .then(&Transform::rotation(self.rotation))
+            .then(&Transform::translation(self.center.0, self.center.1))
+    }
+
+    /// Inverse of [`Self::to_complex()`], built from the view so it stays exact at any zoom
+    pub fn to_pixels(&self, width: usize, height: usize) -> Transform {
+        let scale = 1.0 / self.pixel_size(width, height);
+        Transform::translation(-self.center.0, -self.center.1)
+            .then(&Transform::rotation(-self.rotation))
+            .then(&Transform::scaling(scale, -scale))
+            .then(&Transform::translation(width as f64 / 2.0, height as f64 / 2.0))
+    }
+
+    pub fn pixel_to_complex(&self, pixel: (f64, f64), width: usize, height: usize) -> (f64, f64) {
+        self.to_complex(width, height).apply(pixel)
+    }
+
+    pub fn complex_to_pixel(&self, point: (f64, f64), width: usize, height: usize) -> (f64, f64) {
+        self.to_pixels(width, height).apply(point)
+    }
+
+    /// Multiply zoom by `factor`, the point under `pixel` (e.g. mouse cursor) stays in place
+    pub fn zoom_at(&mut self, pixel: (f64, f64), factor: f64, width: usize, height: usize) {
+        let anchor = self.pixel_to_complex(pixel, width, height);
+        self.zoom *= factor;
+        let moved = self.pixel_to_complex(pixel, width, height);
+        self.center = (self.center.0 + anchor.0 - moved.0, self.center.1 + anchor.1 - moved.1);
+    }
+
+    /// Move the picture by `delta` pixels, e.g. mouse drag
+    pub fn pan(&mut self, delta: (f64, f64), width: usize, height: usize) {
+        let (dx, dy) = self.to_complex(width, height).apply_vector(delta);
+        self.center = (self.center.0 - dx, self.center.1 - dy);
+    }
+
+    /// Complex plane corners of the image: top left, top right, bottom right, bottom left
+    pub fn corners(&self, width: usize, height: usize) -> [(f64, f64); 4] {
+        let transform = self.to_complex(width, height);
+        let (w, h) = (width as f64, height as f64);
+        [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)].map(|corner| transform.apply(corner))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels_round_trip_at_any_zoom() {
+        for zoom in [1.0, 3.5e5, 1e12] {
+            let view = FractalView::new((-0.745, 0.113), zoom).with_rotation(0.4);
+            for pixel in [(0.0, 0.0), (960.5, 540.5), (1919.0, 17.0)] {
+                let back = view.complex_to_pixel(view.pixel_to_complex(pixel, 1920, 1080), 1920, 1080);
+                assert!((back.0 - pixel.0).abs() < 1e-3 && (back.1 - pixel.1).abs() < 1e-3, "{:?} at {}", back, zoom);
+            }
+        }
+    }
+
+    #[test]
+    fn centre_is_in_the_middle() {
+        let view = FractalView::new((0.25, -0.5), 1e9);
+        let middle = view.complex_to_pixel(view.center, 800, 600);
+        assert!((middle.0 - 400.0).abs() < 1e-6 && (middle.1 - 300.0).abs() < 1e-6);
+    }
+}
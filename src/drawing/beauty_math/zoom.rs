@@ -0,0 +1,367 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use super::super::clip::Rect;
+use super::super::framebuffer::Framebuffer;
+use super::super::image::ScaleFilter;
+use super::coloring::{mandelbrot_orbit, FractalColors, Orbit, OrbitTrap};
+use super::double_double::{DoubleDouble, ParseDoubleDoubleError};
+use super::parallel;
+use super::perturbation::ReferenceOrbit;
+use super::view::FractalView;
+
+/// Below this pixel size `f64` can not tell neighbour pixels apart, perturbation is used instead
+pub const PERTURBATION_PIXEL_SIZE: f64 = 1e-12;
+
+/// Deepest zoom [`DoubleDouble`] reference points can handle
+pub const MAX_ZOOM: f64 = 1e26;
+
+/// Deepest zoom of [`ZoomTarget::discover()`], it iterates in `f64`
+const DISCOVERY_ZOOM: f64 = 1e11;
+
+/// Side of the grid sampled to estimate the iterations a keyframe needs
+const ESTIMATE_GRID: usize = 12;
+
+/// Point to zoom into
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ZoomTarget {
+    pub center: (DoubleDouble, DoubleDouble),
+    /// Zoom where the point runs out of precision or details
+    pub max_zoom: f64,
+}
+
+impl ZoomTarget {
+    /// Coordinates in decimal notation, all digits are kept
+    pub fn new(re: &str, im: &str, max_zoom: f64) -> Result<Self, ParseDoubleDoubleError> {
+        Ok(Self { center: (re.parse()?, im.parse()?), max_zoom: max_zoom.min(MAX_ZOOM) })
+    }
+
+    /// Seahorse valley and Misiurewicz points (boundary points with infinitely repeating spirals)
+    pub fn curated() -> Vec<ZoomTarget> {
+        const TARGETS: [(&str, &str); 5] = [
+            ("-0.743643887037158704752191506114774", "0.131825904205311970493132056385139"),
+            // M(4, 1)
+            ("-0.101096363845622161025785445738623", "0.956286510809141500771096057729977"),
+            // M(23, 2)
+            ("-0.776610592599701856564039502552995", "0.134608961675028166056737270233058"),
+            // M(3, 1), tip of the main antenna spiral
+            ("-1.543689012692076361570855971801748", "0"),
+            // M(1, 2), dendrite at i
+            ("0", "1"),
+        ];
+        TARGETS.iter().filter_map(|(re, im)| ZoomTarget::new(re, im, MAX_ZOOM).ok()).collect()
+    }
+
+    /// Random boundary point with details at least down to `1e11` zoom. Zooms in steps of 8,
+    /// each time picking one of the slowest escaping points on a coarse grid
+    pub fn discover<R: Rng + ?Sized>(rng: &mut R) -> ZoomTarget {
+        const GRID: usize = 24;
+        let mut view = FractalView::new((-0.5, 0.0), 1.0);
+
+        while view.zoom < DISCOVERY_ZOOM {
+            let max_iter = max_iterations(view.zoom, 200, 32);
+            let to_complex = view.to_complex(GRID, GRID);
+            let mut candidates: Vec<((f64, f64), u32)> = (0..GRID * GRID)
+                .filter_map(|i| {
+                    let c = to_complex.apply(((i % GRID) as f64 + 0.5, (i / GRID) as f64 + 0.5));
+                    let orbit = mandelbrot_orbit(c, max_iter, None);
+                    orbit.escaped.then_some((c, orbit.iterations))
+                })
+                .collect();
+            if candidates.is_empty() {
+                break;
+            }
+
+            candidates.sort_by_key(|&(_, iterations)| std::cmp::Reverse(iterations));
+            let best = candidates.len().min(4);
+            let &(center, _) = candidates[..best].choose(rng).unwrap_or(&candidates[0]);
+            view.center = center;
+            view.zoom *= 8.0;
+        }
+
+        ZoomTarget { center: (view.center.0.into(), view.center.1.into()), max_zoom: view.zoom.min(DISCOVERY_ZOOM) }
+    }
+}
+
+/// Iteration limit grows with depth, deeper levels need more iterations to show details
+fn max_iterations(zoom: f64, base: u32, per_level: u32) -> u32 {
+    base + (zoom.max(1.0).log2() * per_level as f64) as u32
+}
+
+/// Orbit of `target.center + delta`, with perturbation if there is a `reference` orbit
+#[inline(always)]
+fn target_orbit(
+    target: &ZoomTarget,
+    reference: Option<&ReferenceOrbit>,
+    delta: (f64, f64),
+    max_iter: u32,
+    trap: Option<&OrbitTrap>,
+) -> Orbit {
+    match reference {
+        Some(reference) => reference.orbit(delta, max_iter, trap),
+        None => {
+            let c = (target.center.0.to_f64() + delta.0, target.center.1.to_f64() + delta.1);
+            mandelbrot_orbit(c, max_iter, trap)
+        }
+    }
+}
+
+/// Frame rendered at zoom `2^level`. Frames between two levels are scaled copies of the keyframe
+#[derive(Clone, Debug)]
+struct Keyframe {
+    level: u32,
+    frame: Framebuffer,
+    /// Rows rendered so far, keyframes are filled progressively
+    rows_done: usize,
+    max_iter: u32,
+    /// For zooms beyond `f64` precision
+    reference: Option<ReferenceOrbit>,
+}
+
+/// Live wallpaper zooming into the Mandelbrot set forever.
+///
+/// A keyframe is rendered at every doubling of the zoom, frames in between reuse it scaled up.
+/// The next keyframe is rendered progressively over the frames before it is needed, so a frame
+/// never renders a whole image. Deep zooms switch to perturbation with a [`DoubleDouble`] reference orbit.
+/// Targets are visited one after another, optionally followed by random discovered points.
+///
+/// Example:
+/// ```
+/// let colors = FractalColors::from_colormap(Coloring::Smooth, Colormap::Twilight).with_cycle(48.0);
+/// let mut scene = ZoomScene::new(width, height, colors);
+/// // every frame
+/// scene.update(delta_time);
+/// scene.draw(&mut framebuffer);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ZoomScene {
+    /// Use a non-zero [`FractalColors::cycle`], otherwise colors jump between keyframes as `max_iter` grows
+    pub colors: FractalColors,
+    /// Zoom doublings per second
+    pub speed: f64,
+    /// Iterations at zoom 1
+    pub base_iterations: u32,
+    /// Iterations added per zoom doubling
+    pub iterations_per_level: u32,
+    width: usize,
+    height: usize,
+    targets: Vec<ZoomTarget>,
+    target: usize,
+    discover: bool,
+    rng: StdRng,
+    zoom: f64,
+    current: Option<Keyframe>,
+    next: Option<Keyframe>,
+}
+
+impl ZoomScene {
+    /// Zooms into [`ZoomTarget::curated()`] points
+    pub fn new(width: usize, height: usize, colors: FractalColors) -> Self {
+        Self {
+            colors,
+            speed: 0.5,
+            base_iterations: 200,
+            iterations_per_level: 32,
+            width,
+            height,
+            targets: ZoomTarget::curated(),
+            target: 0,
+            discover: false,
+            rng: StdRng::from_entropy(),
+            zoom: 1.0,
+            current: None,
+            next: None,
+        }
+    }
+
+    /// Visit `targets` in order instead of the curated ones. Empty list enables discovery
+    pub fn with_targets(mut self, targets: Vec<ZoomTarget>) -> Self {
+        self.discover |= targets.is_empty();
+        self.targets = targets;
+        self.target = 0;
+        self.reset();
+        self
+    }
+
+    /// After the last target continue with random [`ZoomTarget::discover()`] points, reproducible with `seed`
+    pub fn with_discovery(mut self, seed: u64) -> Self {
+        self.discover = true;
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    pub fn target(&self) -> Option<&ZoomTarget> {
+        self.targets.get(self.target)
+    }
+
+    /// Rendered keyframes are dropped
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.reset();
+    }
+
+    /// `delta_time` in seconds
+    pub fn update(&mut self, delta_time: f64) {
+        self.zoom *= (self.speed * delta_time).exp2();
+        let max_zoom = self.target().map_or(0.0, |target| target.max_zoom);
+        if self.zoom >= max_zoom {
+            self.next_target();
+        }
+
+        self.prepare_current();
+        // Spread the next keyframe over the time until it is needed, with a margin
+        let progress = self.zoom.log2() - self.level() as f64;
+        let rows = ((progress * 1.5).min(1.0) * self.height as f64).ceil() as usize;
+        if let Some(mut next) = self.next.take() {
+            self.render_rows(&mut next, rows);
+            self.next = Some(next);
+        }
+    }
+
+    /// Draw the current zoom over the whole `target`, which should have the size given to the scene
+    pub fn draw(&mut self, target: &mut Framebuffer) {
+        self.prepare_current();
+        let Some(current) = &self.current else {
+            return;
+        };
+
+        let scale = self.zoom / (current.level as f64).exp2();
+        let (width, height) = (target.width() as f64 * scale, target.height() as f64 * scale);
+        let left = (target.width() as f64 - width) / 2.0;
+        let top = (target.height() as f64 - height) / 2.0;
+        target.draw_image(&current.frame, Rect::new(left, top, left + width, top + height), ScaleFilter::Bilinear);
+    }
+
+    fn level(&self) -> u32 {
+        self.zoom.max(1.0).log2().floor() as u32
+    }
+
+    fn reset(&mut self) {
+        self.zoom = 1.0;
+        self.current = None;
+        self.next = None;
+    }
+
+    fn next_target(&mut self) {
+        self.target += 1;
+        if self.target >= self.targets.len() {
+            if self.discover {
+                let target = ZoomTarget::discover(&mut self.rng);
+                self.targets.push(target);
+                // Coming from an empty list the index is past the only target
+                self.target = self.targets.len() - 1;
+            } else {
+                self.target = 0;
+            }
+        }
+        self.reset();
+    }
+
+    /// Make sure the keyframe of the current level is complete and the next one is started
+    fn prepare_current(&mut self) {
+        if self.target().is_none() {
+            if !self.discover {
+                return;
+            }
+            self.next_target();
+        }
+
+        let level = self.level();
+        if self.current.as_ref().is_some_and(|current| current.level == level) {
+            return;
+        }
+
+        let mut current = match self.next.take() {
+            Some(next) if next.level == level => next,
+            _ => self.keyframe(level),
+        };
+        self.render_rows(&mut current, self.height);
+        self.current = Some(current);
+        self.next = Some(self.keyframe(level + 1));
+    }
+
+    fn keyframe(&self, level: u32) -> Keyframe {
+        let zoom = (level as f64).exp2();
+        let view = FractalView::new((0.0, 0.0), zoom);
+        let max_iter = max_iterations(zoom, self.base_iterations, self.iterations_per_level);
+        let frame = Framebuffer::new(self.width, self.height);
+        let Some(target) = self.target() else {
+            return Keyframe { level, frame, rows_done: 0, max_iter, reference: None };
+        };
+
+        // Some points need far more iterations than the formula gives, the limit is raised to twice
+        // the upper quartile of escape times on a coarse grid
+        let sample_limit = max_iter * 8;
+        let reference = (view.pixel_size(self.width, self.height) < PERTURBATION_PIXEL_SIZE)
+            .then(|| ReferenceOrbit::new(target.center, sample_limit));
+        let to_delta = view.to_complex(ESTIMATE_GRID, ESTIMATE_GRID);
+        let mut escaped: Vec<u32> = (0..ESTIMATE_GRID * ESTIMATE_GRID)
+            .map(|i| {
+                let delta = to_delta.apply(((i % ESTIMATE_GRID) as f64 + 0.5, (i / ESTIMATE_GRID) as f64 + 0.5));
+                target_orbit(target, reference.as_ref(), delta, sample_limit, None)
+            })
+            .filter(|orbit| orbit.escaped)
+            .map(|orbit| orbit.iterations)
+            .collect();
+        escaped.sort_unstable();
+        let quartile = escaped.get(escaped.len() * 3 / 4).copied().unwrap_or(0);
+        let max_iter = max_iter.max(quartile.saturating_mul(2)).min(sample_limit);
+
+        Keyframe { level, frame, rows_done: 0, max_iter, reference }
+    }
+
+    /// Render rows of `keyframe` up to `rows`
+    fn render_rows(&self, keyframe: &mut Keyframe, rows: usize) {
+        let rows = rows.min(self.height);
+        let Some(target) = self.target() else {
+            return;
+        };
+        if rows <= keyframe.rows_done {
+            return;
+        }
+
+        let (width, height) = (self.width, self.height);
+        let from = keyframe.rows_done;
+        // Offsets from the target, the centre itself may need more precision than `f64` has
+        let view = FractalView::new((0.0, 0.0), (keyframe.level as f64).exp2());
+        let to_delta = view.to_complex(width, height);
+        let pixel_size = view.pixel_size(width, height);
+        let (colors, max_iter, reference) = (&self.colors, keyframe.max_iter, keyframe.reference.as_ref());
+        let trap = colors.trap();
+
+        let pixels = &mut keyframe.frame.pixels_mut()[from * width..rows * width];
+        parallel::render_rows(width, rows - from, pixels, |x, y| {
+            let delta = to_delta.apply((x as f64 + 0.5, (y + from) as f64 + 0.5));
+            let orbit = target_orbit(target, reference, delta, max_iter, trap);
+            colors.pixel(&orbit, max_iter, pixel_size)
+        });
+        keyframe.rows_done = rows;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::coloring::Coloring;
+    use super::super::super::colors::colormap::Colormap;
+    use super::*;
+
+    #[test]
+    fn empty_target_list_discovers_and_uses_targets() {
+        let colors = FractalColors::from_colormap(Coloring::Smooth, Colormap::Viridis);
+        let mut scene = ZoomScene::new(16, 16, colors).with_targets(Vec::new()).with_discovery(5);
+        scene.update(0.0);
+        assert!(scene.target().is_some());
+        assert_eq!(scene.targets.len(), 1);
+
+        scene.update(0.1);
+        scene.update(0.1);
+        assert_eq!(scene.targets.len(), 1);
+        assert!(scene.current.is_some());
+    }
+}
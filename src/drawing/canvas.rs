@@ -0,0 +1,220 @@
+use std::f64::consts::PI;
+
+use super::clip::{ClipRegion, Rect};
+use super::text::{Font, TextStyle};
+use super::transform::{Transform, TransformStack};
+
+/// Drawing backend. Works in device (pixel) coordinates, <b>colors are COLORREF</b> (`0x00BBGGRR`), as returned by
+/// [`super::colors::random_color()`] and [`super::colors::interpolate_colors()`].
+///
+/// Implemented by [`super::primitives::GdiSurface`] and software [`super::framebuffer::Framebuffer`].
+/// Usually used through [`Canvas`], which applies the transform stack to every primitive.
+pub trait Surface {
+    /// (width, height) in pixels
+    fn size(&self) -> (usize, usize);
+
+    /// Restrict all following drawing to `clip` (in device coordinates). `None` removes clipping
+    fn set_clip(&mut self, clip: Option<&ClipRegion>);
+
+    fn draw_line(&mut self, from: (f64, f64), to: (f64, f64), color: u32);
+
+    fn draw_polyline(&mut self, points: &[(f64, f64)], color: u32) {
+        for segment in points.windows(2) {
+            self.draw_line(segment[0], segment[1], color);
+        }
+    }
+
+    /// Fill polygon with even-odd rule
+    fn fill_polygon(&mut self, points: &[(f64, f64)], color: u32);
+
+    fn fill_circle(&mut self, center: (f64, f64), radius: f64, color: u32);
+
+    /// Draw multi-line `text`, `position` is the anchor of the first line top (see [`super::text::TextAlign`])
+    fn draw_text(&mut self, font: &Font, text: &str, position: (f64, f64), style: &TextStyle);
+}
+
+/// [`Surface`] with [`TransformStack`] and clip region. All coordinates passed to canvas are local and transformed
+/// by the current transform, so scenes can be authored in any coordinate system:
+/// ```
+/// let mut canvas = Canvas::normalized(GdiSurface::new(hdc, width, height));
+/// canvas.push();
+/// canvas.translate(0.5, 0.5);
+/// canvas.rotate(angle);
+/// canvas.draw_line((0.0, 0.0), (0.25, 0.0), 0xFFFFFF);
+/// canvas.pop();
+/// ```
+///
+/// [`Canvas::push()`] and [`Canvas::pop()`] save and restore both transform and clip region.
+pub struct Canvas<S: Surface> {
+    surface: S,
+    transforms: TransformStack,
+    clip: Option<ClipRegion>,
+    saved_clips: Vec<Option<ClipRegion>>,
+}
+
+impl<S: Surface> Canvas<S> {
+    /// Local coordinates are the surface pixels
+    pub fn new(surface: S) -> Self {
+        Self::with_transform(surface, Transform::IDENTITY)
+    }
+
+    /// Local coordinates are `[0, 1] x [0, 1]` for the whole surface
+    pub fn normalized(surface: S) -> Self {
+        let (width, height) = surface.size();
+        Self::with_transform(surface, Transform::normalized(width, height))
+    }
+
+    /// `base` maps local coordinates to the surface pixels
+    pub fn with_transform(mut surface: S, base: Transform) -> Self {
+        surface.set_clip(None);
+        Self { surface, transforms: TransformStack::with_base(base), clip: None, saved_clips: Vec::new() }
+    }
+
+    pub fn surface(&self) -> &S {
+        &self.surface
+    }
+
+    pub fn surface_mut(&mut self) -> &mut S {
+        &mut self.surface
+    }
+
+    pub fn into_surface(self) -> S {
+        self.surface
+    }
+
+    pub fn width(&self) -> usize {
+        self.surface.size().0
+    }
+
+    pub fn height(&self) -> usize {
+        self.surface.size().1
+    }
+
+    pub fn transforms(&self) -> &TransformStack {
+        &self.transforms
+    }
+
+    pub fn transforms_mut(&mut self) -> &mut TransformStack {
+        &mut self.transforms
+    }
+
+    pub fn push(&mut self) {
+        self.transforms.push();
+        self.saved_clips.push(self.clip.clone());
+    }
+
+    pub fn pop(&mut self) -> bool {
+        if let Some(clip) = self.saved_clips.pop() {
+            self.clip = clip;
+            self.surface.set_clip(self.clip.as_ref());
+        }
+        self.transforms.pop()
+    }
+
+    pub fn translate(&mut self, x: f64, y: f64) {
+        self.transforms.translate(x, y);
+    }
+
+    pub fn rotate(&mut self, angle: f64) {
+        self.transforms.rotate(angle);
+    }
+
+    pub fn scale(&mut self, sx: f64, sy: f64) {
+        self.transforms.scale(sx, sy);
+    }
+
+    pub fn clip(&self) -> Option<&ClipRegion> {
+        self.clip.as_ref()
+    }
+
+    /// Intersect clip region with local rectangle. Rotated rectangle becomes a path clip
+    pub fn clip_rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        let transform = self.transforms.current();
+        let region = if transform.is_axis_aligned() {
+            let corners = [transform.apply((x, y)), transform.apply((x + width, y + height))];
+            ClipRegion::rect(Rect::bounding(&corners))
+        } else {
+            ClipRegion::path(self.transform_points(&[(x, y), (x + width, y), (x + width, y + height), (x, y + height)]))
+        };
+        self.intersect_clip(region);
+    }
+
+    /// Intersect clip region with local polygon (even-odd rule)
+    pub fn clip_path(&mut self, points: &[(f64, f64)]) {
+        let region = ClipRegion::path(self.transform_points(points));
+        self.intersect_clip(region);
+    }
+
+    /// Remove clipping. Saved states are not changed
+    pub fn reset_clip(&mut self) {
+        self.clip = None;
+        self.surface.set_clip(None);
+    }
+
+    fn intersect_clip(&mut self, region: ClipRegion) {
+        let clip = match &self.clip {
+            Some(clip) => clip.intersect(&region),
+            None => region,
+        };
+        self.surface.set_clip(Some(&clip));
+        self.clip = Some(clip);
+    }
+
+    pub fn draw_line(&mut self, from: (f64, f64), to: (f64, f64), color: u32) {
+        let from = self.transforms.apply(from);
+        let to = self.transforms.apply(to);
+        self.surface.draw_line(from, to, color);
+    }
+
+    pub fn draw_polyline(&mut self, points: &[(f64, f64)], color: u32) {
+        let points = self.transform_points(points);
+        self.surface.draw_polyline(&points, color);
+    }
+
+    pub fn fill_polygon(&mut self, points: &[(f64, f64)], color: u32) {
+        let points = self.transform_points(points);
+        self.surface.fill_polygon(&points, color);
+    }
+
+    /// Circle stays a circle for rotation and uniform scale, otherwise it is drawn as a transformed polygon
+    pub fn fill_circle(&mut self, center: (f64, f64), radius: f64, color: u32) {
+        let transform = *self.transforms.current();
+        if transform.is_similarity() {
+            self.surface.fill_circle(transform.apply(center), radius * transform.scale_factor(), color);
+            return;
+        }
+
+        let device_radius = radius * transform.a.hypot(transform.b).max(transform.c.hypot(transform.d));
+        let segments = (device_radius * PI).clamp(12.0, 256.0) as usize;
+        let points: Vec<(f64, f64)> = (0..segments)
+            .map(|i| {
+                let angle = 2.0 * PI * i as f64 / segments as f64;
+                transform.apply((center.0 + radius * angle.cos(), center.1 + radius * angle.sin()))
+            })
+            .collect();
+        self.surface.fill_polygon(&points, color);
+    }
+
+    pub fn fill_rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: u32) {
+        self.fill_polygon(&[(x, y), (x + width, y), (x + width, y + height), (x, y + height)], color);
+    }
+
+    /// Text position is transformed and size is scaled, but glyphs are not rotated or skewed
+    pub fn draw_text(&mut self, font: &Font, text: &str, position: (f64, f64), style: &TextStyle) {
+        let transform = self.transforms.current();
+        let style = TextStyle { size: style.size * transform.scale_factor(), ..*style };
+        let position = transform.apply(position);
+        self.surface.draw_text(font, text, position, &style);
+    }
+
+    /// Fill the whole surface, ignoring the transform. Clip region is respected
+    pub fn clear(&mut self, color: u32) {
+        let (width, height) = self.surface.size();
+        let (width, height) = (width as f64, height as f64);
+        self.surface.fill_polygon(&[(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)], color);
+    }
+
+    fn transform_points(&self, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        points.iter().map(|p| self.transforms.apply(*p)).collect()
+    }
+}
@@ -0,0 +1,265 @@
+/// Axis-aligned rectangle in device coordinates. `right` and `bottom` are exclusive
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+impl Rect {
+    #[inline(always)]
+    pub const fn new(left: f64, top: f64, right: f64, bottom: f64) -> Self {
+        Self { left, top, right, bottom }
+    }
+
+    pub const fn from_size(width: usize, height: usize) -> Self {
+        Self::new(0.0, 0.0, width as f64, height as f64)
+    }
+
+    /// Bounding box of `points`. Empty rect for empty `points`
+    pub fn bounding(points: &[(f64, f64)]) -> Self {
+        if points.is_empty() {
+            return Self::default();
+        }
+        points.iter().fold(
+            Self::new(f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            |r, p| Self::new(r.left.min(p.0), r.top.min(p.1), r.right.max(p.0), r.bottom.max(p.1)),
+        )
+    }
+
+    pub fn width(&self) -> f64 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> f64 {
+        self.bottom - self.top
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.right <= self.left || self.bottom <= self.top
+    }
+
+    pub fn contains(&self, point: (f64, f64)) -> bool {
+        point.0 >= self.left && point.0 < self.right && point.1 >= self.top && point.1 < self.bottom
+    }
+
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        Rect::new(
+            self.left.max(other.left),
+            self.top.max(other.top),
+            self.right.min(other.right),
+            self.bottom.min(other.bottom),
+        )
+    }
+}
+
+/// Clip segment to `rect` with Liang–Barsky algorithm. Returns `None` if segment is fully outside
+pub fn clip_line(rect: &Rect, from: (f64, f64), to: (f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let mut t0 = 0.0f64;
+    let mut t1 = 1.0f64;
+
+    // (p, q) for left, right, top and bottom edges: point is inside if `p * t <= q`
+    let edges = [
+        (-dx, from.0 - rect.left),
+        (dx, rect.right - from.0),
+        (-dy, from.1 - rect.top),
+        (dy, rect.bottom - from.1),
+    ];
+    for (p, q) in edges {
+        if p == 0.0 {
+            // Parallel to the edge
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+    }
+
+    Some(((from.0 + t0 * dx, from.1 + t0 * dy), (from.0 + t1 * dx, from.1 + t1 * dy)))
+}
+
+/// Clip polygon to `rect` with Sutherland–Hodgman algorithm
+pub fn clip_polygon(rect: &Rect, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let points = clip_polygon_edge(points, |p| p.0 >= rect.left, |a, b| {
+        (rect.left, a.1 + (b.1 - a.1) * (rect.left - a.0) / (b.0 - a.0))
+    });
+    let points = clip_polygon_edge(&points, |p| p.0 <= rect.right, |a, b| {
+        (rect.right, a.1 + (b.1 - a.1) * (rect.right - a.0) / (b.0 - a.0))
+    });
+    let points = clip_polygon_edge(&points, |p| p.1 >= rect.top, |a, b| {
+        (a.0 + (b.0 - a.0) * (rect.top - a.1) / (b.1 - a.1), rect.top)
+    });
+    clip_polygon_edge(&points, |p| p.1 <= rect.bottom, |a, b| {
+        (a.0 + (b.0 - a.0) * (rect.bottom - a.1) / (b.1 - a.1), rect.bottom)
+    })
+}
+
+fn clip_polygon_edge(
+    points: &[(f64, f64)],
+    inside: impl Fn((f64, f64)) -> bool,
+    intersect: impl Fn((f64, f64), (f64, f64)) -> (f64, f64),
+) -> Vec<(f64, f64)> {
+    let mut output = Vec::with_capacity(points.len() + 2);
+    for (i, &current) in points.iter().enumerate() {
+        let previous = points[(i + points.len() - 1) % points.len()];
+        match (inside(previous), inside(current)) {
+            (true, true) => output.push(current),
+            (true, false) => output.push(intersect(previous, current)),
+            (false, true) => {
+                output.push(intersect(previous, current));
+                output.push(current);
+            }
+            (false, false) => {}
+        }
+    }
+    output
+}
+
+/// Even-odd point in polygon test
+pub fn polygon_contains(points: &[(f64, f64)], point: (f64, f64)) -> bool {
+    let mut inside = false;
+    for (i, p0) in points.iter().enumerate() {
+        let p1 = points[(i + 1) % points.len()];
+        if (p0.1 <= point.1) != (p1.1 <= point.1)
+            && point.0 < p0.0 + (point.1 - p0.1) / (p1.1 - p0.1) * (p1.0 - p0.0)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Spans `[from, to)` of the horizontal line `y`, that are inside polygon (even-odd rule)
+pub fn polygon_row_spans(points: &[(f64, f64)], y: f64, spans: &mut Vec<(f64, f64)>) {
+    let mut crossings = Vec::new();
+    for (i, p0) in points.iter().enumerate() {
+        let p1 = points[(i + 1) % points.len()];
+        if (p0.1 <= y) != (p1.1 <= y) {
+            crossings.push(p0.0 + (y - p0.1) / (p1.1 - p0.1) * (p1.0 - p0.0));
+        }
+    }
+    crossings.sort_by(|a, b| a.total_cmp(b));
+
+    spans.clear();
+    spans.extend(crossings.chunks_exact(2).map(|span| (span[0], span[1])));
+}
+
+/// Intersection of two sorted lists of non-overlapping spans
+pub fn intersect_spans(a: &[(f64, f64)], b: &[(f64, f64)], result: &mut Vec<(f64, f64)>) {
+    result.clear();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let from = a[i].0.max(b[j].0);
+        let to = a[i].1.min(b[j].1);
+        if from < to {
+            result.push((from, to));
+        }
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+}
+
+/// Area where drawing is allowed, in device coordinates: intersection of `bounds` and all `paths`.
+///
+/// Paths are polygons filled with even-odd rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClipRegion {
+    pub bounds: Rect,
+    pub paths: Vec<Vec<(f64, f64)>>,
+}
+
+impl ClipRegion {
+    pub fn rect(rect: Rect) -> Self {
+        Self { bounds: rect, paths: Vec::new() }
+    }
+
+    pub fn path(points: Vec<(f64, f64)>) -> Self {
+        Self { bounds: Rect::bounding(&points), paths: vec![points] }
+    }
+
+    pub fn is_rect(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Intersect with `other`, result allows drawing only where both regions allow it
+    pub fn intersect(&self, other: &ClipRegion) -> ClipRegion {
+        let mut paths = self.paths.clone();
+        paths.extend(other.paths.iter().cloned());
+        ClipRegion { bounds: self.bounds.intersect(&other.bounds), paths }
+    }
+
+    pub fn contains(&self, point: (f64, f64)) -> bool {
+        self.bounds.contains(point) && self.paths.iter().all(|path| polygon_contains(path, point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECT: Rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+
+    #[test]
+    fn line_inside_is_unchanged() {
+        assert_eq!(clip_line(&RECT, (1.0, 2.0), (8.0, 9.0)), Some(((1.0, 2.0), (8.0, 9.0))));
+    }
+
+    #[test]
+    fn line_outside_is_dropped() {
+        assert_eq!(clip_line(&RECT, (-5.0, -1.0), (-1.0, 20.0)), None);
+        assert_eq!(clip_line(&RECT, (11.0, 1.0), (20.0, 1.0)), None);
+        // Crosses the lines of two edges, but passes the corner outside
+        assert_eq!(clip_line(&RECT, (-2.0, 1.0), (1.0, -2.0)), None);
+    }
+
+    #[test]
+    fn crossing_line_is_cut_at_the_edges() {
+        assert_eq!(clip_line(&RECT, (-5.0, 5.0), (15.0, 5.0)), Some(((0.0, 5.0), (10.0, 5.0))));
+        assert_eq!(clip_line(&RECT, (5.0, 5.0), (5.0, 20.0)), Some(((5.0, 5.0), (5.0, 10.0))));
+        assert_eq!(clip_line(&RECT, (-10.0, -10.0), (20.0, 20.0)), Some(((0.0, 0.0), (10.0, 10.0))));
+    }
+
+    #[test]
+    fn degenerate_line_is_a_point() {
+        assert_eq!(clip_line(&RECT, (3.0, 3.0), (3.0, 3.0)), Some(((3.0, 3.0), (3.0, 3.0))));
+        assert_eq!(clip_line(&RECT, (-3.0, 3.0), (-3.0, 3.0)), None);
+    }
+
+    #[test]
+    fn polygon_inside_is_unchanged() {
+        let triangle = [(1.0, 1.0), (9.0, 1.0), (5.0, 8.0)];
+        assert_eq!(clip_polygon(&RECT, &triangle), triangle);
+    }
+
+    #[test]
+    fn crossing_polygon_is_cut_to_the_rect() {
+        let square = [(-5.0, -5.0), (5.0, -5.0), (5.0, 5.0), (-5.0, 5.0)];
+        let clipped = clip_polygon(&RECT, &square);
+        assert_eq!(Rect::bounding(&clipped), Rect::new(0.0, 0.0, 5.0, 5.0));
+        assert!(clipped.iter().all(|&(x, y)| (0.0..=5.0).contains(&x) && (0.0..=5.0).contains(&y)));
+
+        let covering = [(-1.0, -1.0), (11.0, -1.0), (11.0, 11.0), (-1.0, 11.0)];
+        assert_eq!(Rect::bounding(&clip_polygon(&RECT, &covering)), RECT);
+    }
+
+    #[test]
+    fn polygon_outside_is_empty() {
+        assert!(clip_polygon(&RECT, &[(11.0, 1.0), (20.0, 1.0), (15.0, 8.0)]).is_empty());
+        assert!(clip_polygon(&RECT, &[(-5.0, -5.0), (-1.0, -5.0), (-1.0, 20.0)]).is_empty());
+        assert!(clip_polygon(&RECT, &[]).is_empty());
+    }
+}
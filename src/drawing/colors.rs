@@ -1,3 +1,16 @@
+pub mod spaces;
+pub mod gradient;
+pub mod compositing;
+pub mod parse;
+pub mod palette;
+pub mod random;
+pub mod harmony;
+pub mod colormap;
+pub mod accessibility;
+
+use spaces::ColorSpace;
+use gradient::Gradient;
+
 #[repr(C)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -17,6 +30,133 @@ impl<T> RGB<T> {
     }
 }
 
+/// Color with alpha. Alpha is straight (not premultiplied) unless stated otherwise,
+/// see [`compositing`] for premultiplied colors and blending
+#[repr(C)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct RGBA<ComponentType> {
+    /// Red
+    pub r: ComponentType,
+    /// Green
+    pub g: ComponentType,
+    /// Blue
+    pub b: ComponentType,
+    /// Alpha, 0 is transparent
+    pub a: ComponentType,
+}
+
+impl<T> RGBA<T> {
+    #[inline(always)]
+    pub const fn new(r: T, g: T, b: T, a: T) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub fn rgb(self) -> RGB<T> {
+        RGB::new(self.r, self.g, self.b)
+    }
+}
+
+impl<T> RGB<T> {
+    pub fn with_alpha(self, a: T) -> RGBA<T> {
+        RGBA::new(self.r, self.g, self.b, a)
+    }
+}
+
+/// Packed formats:
+///
+/// <b>COLORREF</b> - `0x00BBGGRR`, used by GDI and most of the drawing functions
+///
+/// <b>ARGB</b> - `0xAARRGGBB`, used by 32-bit DIB and [`super::framebuffer::Framebuffer`]
+///
+/// <b>RGBA</b> - `0xRRGGBBAA`, as in `#rrggbbaa` hex notation
+impl RGB<u8> {
+    #[inline(always)]
+    pub const fn to_colorref(self) -> u32 {
+        self.r as u32 | (self.g as u32) << 8 | (self.b as u32) << 16
+    }
+
+    #[inline(always)]
+    pub const fn from_colorref(color: u32) -> Self {
+        Self::new(color as u8, (color >> 8) as u8, (color >> 16) as u8)
+    }
+
+    /// `0x00RRGGBB`
+    #[inline(always)]
+    pub const fn to_pixel(self) -> u32 {
+        (self.r as u32) << 16 | (self.g as u32) << 8 | self.b as u32
+    }
+
+    /// From `0x??RRGGBB`, top byte is ignored
+    #[inline(always)]
+    pub const fn from_pixel(pixel: u32) -> Self {
+        Self::new((pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8)
+    }
+}
+
+impl RGBA<u8> {
+    /// Alpha is dropped
+    #[inline(always)]
+    pub const fn to_colorref(self) -> u32 {
+        RGB::new(self.r, self.g, self.b).to_colorref()
+    }
+
+    /// Opaque color
+    #[inline(always)]
+    pub const fn from_colorref(color: u32) -> Self {
+        let rgb = RGB::from_colorref(color);
+        Self::new(rgb.r, rgb.g, rgb.b, 255)
+    }
+
+    #[inline(always)]
+    pub const fn to_argb(self) -> u32 {
+        (self.a as u32) << 24 | (self.r as u32) << 16 | (self.g as u32) << 8 | self.b as u32
+    }
+
+    #[inline(always)]
+    pub const fn from_argb(color: u32) -> Self {
+        Self::new((color >> 16) as u8, (color >> 8) as u8, color as u8, (color >> 24) as u8)
+    }
+
+    #[inline(always)]
+    pub const fn to_rgba(self) -> u32 {
+        (self.r as u32) << 24 | (self.g as u32) << 16 | (self.b as u32) << 8 | self.a as u32
+    }
+
+    #[inline(always)]
+    pub const fn from_rgba(color: u32) -> Self {
+        Self::new((color >> 24) as u8, (color >> 16) as u8, (color >> 8) as u8, color as u8)
+    }
+
+    /// Components in `[0, 1]`
+    pub fn to_f32(self) -> RGBA<f32> {
+        RGBA::new(self.r as f32 / 255.0, self.g as f32 / 255.0, self.b as f32 / 255.0, self.a as f32 / 255.0)
+    }
+}
+
+impl RGBA<f32> {
+    /// Clamp components to `[0, 1]` and round to bytes
+    pub fn to_u8(self) -> RGBA<u8> {
+        let convert = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        RGBA::new(convert(self.r), convert(self.g), convert(self.b), convert(self.a))
+    }
+}
+
+impl RGB<u8> {
+    /// WCAG relative luminance in `[0, 1]`
+    pub fn relative_luminance(self) -> f32 {
+        let RGB { r, g, b } = self.to_linear();
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+}
+
+/// WCAG contrast ratio from 1 (same luminance) to 21 (black on white), order of colors does not matter
+pub fn contrast_ratio(a: RGB<u8>, b: RGB<u8>) -> f32 {
+    let (a, b) = (a.relative_luminance(), b.relative_luminance());
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Fully random color as COLORREF. See [`random::ColorGenerator`] for seeded and constrained colors
 pub fn random_color() -> u32 {
     winapi::um::wingdi::RGB(
         rand::random::<u8>(),
@@ -25,26 +165,14 @@ pub fn random_color() -> u32 {
     )
 }
 
+/// Evenly spaced `colors`, `weight` in `[0, 1]`. Black for empty `colors`.
+/// See [`Gradient`] for stops, easing and wrap modes
 pub fn interpolate_colors(colors: &[RGB<u8>], weight: f32) -> u32 {
-    let num_colors = colors.len();
-    let segment = 1.0 / (num_colors - 1) as f32;
-
-    // Find the two adjacent colors for the given weight
-    let index1 = (weight / segment).floor() as usize;
-    let index2 = index1 + 1;
-
-    let color1 = colors[index1];
-    let color2 = colors[index2];
-
-    // Calculate the weight within the segment
-    let segment_weight = (weight - index1 as f32 * segment) / segment;
-
-    // Interpolate between the two colors
-    let r = ((1.0 - segment_weight) * color1.r as f32 + segment_weight * color2.r as f32) as u8;
-    let g = ((1.0 - segment_weight) * color1.g as f32 + segment_weight * color2.g as f32) as u8;
-    let b = ((1.0 - segment_weight) * color1.b as f32 + segment_weight * color2.b as f32) as u8;
-
-    winapi::um::wingdi::RGB(r, g, b)
+    if colors.is_empty() {
+        return 0;
+    }
+    let color = Gradient::even(colors).sample(weight);
+    winapi::um::wingdi::RGB(color.r, color.g, color.b)
 }
 
 pub fn mix_colors(colors: &[RGB<u8>], proportions: &[f32]) -> RGB<u8> {
@@ -65,20 +193,25 @@ pub fn mix_colors(colors: &[RGB<u8>], proportions: &[f32]) -> RGB<u8> {
     RGB::new(r as u8, g as u8, b as u8)
 }
 
+/// Evenly spaced `floats`, `weight` in `[0, 1]`. Zero for empty `floats`. See [`Gradient`]
 pub fn interpolate_floats(floats: &[f32], weight: f32) -> f32 {
-    let num_colors = floats.len();
-    let segment = 1.0 / (num_colors - 1) as f32;
-
-    // Find the two adjacent colors for the given weight
-    let index1 = (weight / segment).floor() as usize;
-    let index2 = index1 + 1;
-
-    let float1 = floats[index1];
-    let float2 = floats[index2];
+    if floats.is_empty() {
+        return 0.0;
+    }
+    Gradient::even(floats).sample(weight)
+}
 
-    // Calculate the weight within the segment
-    let segment_weight = (weight - index1 as f32 * segment) / segment;
+/// Like [`interpolate_colors()`], but colors are interpolated in `space`.
+/// For example [`ColorSpace::Oklab`] gives perceptually even gradients without muddy midpoints
+pub fn interpolate_colors_in(colors: &[RGB<u8>], weight: f32, space: ColorSpace) -> u32 {
+    if colors.is_empty() {
+        return 0;
+    }
+    let color = Gradient::even(colors).with_space(space).sample(weight);
+    winapi::um::wingdi::RGB(color.r, color.g, color.b)
+}
 
-    // Interpolate between the two floats
-    (1.0 - segment_weight) * float1 as f32 + segment_weight * float2 as f32
+/// Like [`mix_colors()`], but colors are mixed in `space`
+pub fn mix_colors_in(colors: &[RGB<u8>], proportions: &[f32], space: ColorSpace) -> RGB<u8> {
+    space.mix(colors, proportions)
 }
\ No newline at end of file
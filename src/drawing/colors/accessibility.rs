@@ -0,0 +1,187 @@
+use super::super::framebuffer::Framebuffer;
+use super::harmony::Theme;
+use super::{contrast_ratio, RGB};
+
+/// Dichromatic color vision deficiencies
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ColorBlindness {
+    /// No red cones, red looks dark and similar to green
+    Protanopia,
+    /// No green cones, the most common one
+    Deuteranopia,
+    /// No blue cones, blue looks like green and yellow like pink
+    Tritanopia,
+}
+
+impl ColorBlindness {
+    pub const ALL: [ColorBlindness; 3] =
+        [ColorBlindness::Protanopia, ColorBlindness::Deuteranopia, ColorBlindness::Tritanopia];
+
+    /// Linear RGB transform for full severity, Machado, Oliveira and Fernandes (2009)
+    fn matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            ColorBlindness::Protanopia => [
+                [0.152_286, 1.052_583, -0.204_868],
+                [0.114_503, 0.786_281, 0.099_216],
+                [-0.003_882, -0.048_116, 1.051_998],
+            ],
+            ColorBlindness::Deuteranopia => [
+                [0.367_322, 0.860_646, -0.227_968],
+                [0.280_085, 0.672_501, 0.047_413],
+                [-0.011_820, 0.042_940, 0.968_881],
+            ],
+            ColorBlindness::Tritanopia => [
+                [1.255_528, -0.076_749, -0.178_779],
+                [-0.078_411, 0.930_809, 0.147_602],
+                [0.004_733, 0.691_367, 0.303_900],
+            ],
+        }
+    }
+
+    /// How `color` looks with the deficiency
+    pub fn simulate(self, color: RGB<u8>) -> RGB<u8> {
+        self.simulate_partial(color, 1.0)
+    }
+
+    /// `severity` in `[0, 1]`, 0 is normal vision. Partial deficiencies (anomalous trichromacy)
+    /// are approximated by blending the full transform with the original color
+    pub fn simulate_partial(self, color: RGB<u8>, severity: f32) -> RGB<u8> {
+        let linear = color.to_linear();
+        let input = [linear.r, linear.g, linear.b];
+        let severity = severity.clamp(0.0, 1.0);
+        let simulated = self.matrix().map(|row| row.iter().zip(input).map(|(m, c)| m * c).sum::<f32>());
+        let blend = |i: usize| input[i] + (simulated[i] - input[i]) * severity;
+        RGB::<u8>::from_linear(RGB::new(blend(0), blend(1), blend(2)))
+    }
+
+    /// Simulate the deficiency on every pixel of `framebuffer`, alpha byte is kept
+    pub fn simulate_frame(self, framebuffer: &mut Framebuffer) {
+        for pixel in framebuffer.pixels_mut() {
+            let color = self.simulate(RGB::from_pixel(*pixel));
+            *pixel = (*pixel & 0xFF00_0000) | color.to_pixel();
+        }
+    }
+}
+
+/// WCAG 2 success criteria for text contrast
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum WcagLevel {
+    /// 4.5:1, 3:1 for large text
+    #[default]
+    AA,
+    /// 7:1, 4.5:1 for large text
+    AAA,
+}
+
+impl WcagLevel {
+    /// Minimal contrast ratio. Large text is at least 18pt, or 14pt bold
+    pub fn min_ratio(self, large_text: bool) -> f32 {
+        match (self, large_text) {
+            (WcagLevel::AA, false) => 4.5,
+            (WcagLevel::AA, true) => 3.0,
+            (WcagLevel::AAA, false) => 7.0,
+            (WcagLevel::AAA, true) => 4.5,
+        }
+    }
+}
+
+/// `foreground` over `background` passes `level`
+pub fn meets_wcag(foreground: RGB<u8>, background: RGB<u8>, level: WcagLevel, large_text: bool) -> bool {
+    contrast_ratio(foreground, background) >= level.min_ratio(large_text)
+}
+
+/// Contrast ratio as seen with `deficiency`, `None` is normal vision
+pub fn perceived_contrast(a: RGB<u8>, b: RGB<u8>, deficiency: Option<ColorBlindness>) -> f32 {
+    match deficiency {
+        Some(deficiency) => contrast_ratio(deficiency.simulate(a), deficiency.simulate(b)),
+        None => contrast_ratio(a, b),
+    }
+}
+
+impl Theme {
+    /// The lowest contrast of primary and accents against the background, as seen with `deficiency`
+    pub fn min_contrast(&self, deficiency: Option<ColorBlindness>) -> f32 {
+        self.colors()
+            .into_iter()
+            .map(|color| perceived_contrast(color, self.background, deficiency))
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// All theme colors have at least `ratio` contrast with normal vision and every [`ColorBlindness`]
+    pub fn is_readable(&self, ratio: f32) -> bool {
+        std::iter::once(None)
+            .chain(ColorBlindness::ALL.map(Some))
+            .all(|deficiency| self.min_contrast(deficiency) >= ratio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: RGB<u8> = RGB::new(0, 0, 0);
+    const WHITE: RGB<u8> = RGB::new(255, 255, 255);
+
+    #[test]
+    fn contrast_ratio_spec_values() {
+        assert!((contrast_ratio(BLACK, WHITE) - 21.0).abs() < 1e-3);
+        assert!((contrast_ratio(WHITE, BLACK) - 21.0).abs() < 1e-3);
+        let gray = RGB::new(120, 130, 140);
+        assert!((contrast_ratio(gray, gray) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn wcag_thresholds() {
+        // #767676 is the lightest gray passing AA on white, #777777 just fails
+        assert!(meets_wcag(RGB::new(0x76, 0x76, 0x76), WHITE, WcagLevel::AA, false));
+        assert!(!meets_wcag(RGB::new(0x77, 0x77, 0x77), WHITE, WcagLevel::AA, false));
+        assert!(!meets_wcag(RGB::new(0x76, 0x76, 0x76), WHITE, WcagLevel::AAA, false));
+        assert!(meets_wcag(RGB::new(0x76, 0x76, 0x76), WHITE, WcagLevel::AAA, true));
+        // #595959 is about 7:1
+        assert!(meets_wcag(RGB::new(0x59, 0x59, 0x59), WHITE, WcagLevel::AAA, false));
+        // #949494 is about 3:1, enough only for large AA text
+        assert!(meets_wcag(RGB::new(0x94, 0x94, 0x94), WHITE, WcagLevel::AA, true));
+        assert!(!meets_wcag(RGB::new(0x94, 0x94, 0x94), WHITE, WcagLevel::AA, false));
+    }
+
+    #[test]
+    fn grays_look_the_same_to_everyone() {
+        for deficiency in ColorBlindness::ALL {
+            for value in [0, 64, 128, 200, 255] {
+                let gray = RGB::new(value, value, value);
+                let seen = deficiency.simulate(gray);
+                let close = [seen.r, seen.g, seen.b].iter().all(|c| c.abs_diff(value) <= 2);
+                assert!(close, "{:?} sees {:?} as {:?}", deficiency, gray, seen);
+            }
+        }
+    }
+
+    #[test]
+    fn red_and_green_merge_without_red_or_green_cones() {
+        let (red, green) = (RGB::new(200, 40, 40), RGB::new(40, 140, 40));
+        let distance = |a: RGB<u8>, b: RGB<u8>| {
+            a.r.abs_diff(b.r) as u32 + a.g.abs_diff(b.g) as u32 + a.b.abs_diff(b.b) as u32
+        };
+        for deficiency in [ColorBlindness::Protanopia, ColorBlindness::Deuteranopia] {
+            let (a, b) = (deficiency.simulate(red), deficiency.simulate(green));
+            assert!(distance(a, b) < distance(red, green) / 2, "{:?}: {:?} {:?}", deficiency, a, b);
+        }
+    }
+
+    #[test]
+    fn zero_severity_is_normal_vision() {
+        let color = RGB::new(12, 200, 99);
+        for deficiency in ColorBlindness::ALL {
+            let seen = deficiency.simulate_partial(color, 0.0);
+            assert!(seen.r.abs_diff(color.r) <= 1 && seen.g.abs_diff(color.g) <= 1 && seen.b.abs_diff(color.b) <= 1);
+        }
+    }
+
+    #[test]
+    fn simulate_frame_keeps_alpha() {
+        let mut frame = Framebuffer::from_pixels(2, 1, vec![0x80FF0000, 0x00808080]).with_alpha(true);
+        ColorBlindness::Deuteranopia.simulate_frame(&mut frame);
+        assert_eq!(frame.pixels()[0] >> 24, 0x80);
+        assert_eq!(frame.pixels()[1] >> 24, 0);
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::gradient::{Gradient, GradientLut, WrapMode};
+use super::spaces::ColorSpace;
+use super::RGB;
+
+/// Perceptually uniform and cyclic colormaps for scalar fields (fractals, heatmaps).
+///
+/// Example:
+/// ```
+/// let colormap: Colormap = "magma".parse()?;
+/// let lut = colormap.to_lut(256);
+/// let color = lut.sample(value);
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Colormap {
+    #[default]
+    Viridis,
+    Magma,
+    Inferno,
+    Plasma,
+    /// Rainbow-like, high contrast, but not uniform in lightness
+    Turbo,
+    /// Readable with red-green color blindness
+    Cividis,
+    /// Cyclic, for periodic values like angles or smooth iteration counts
+    Twilight,
+}
+
+/// Coefficients of 6th degree polynomial fits to matplotlib tables (Matt Zucker), `c0 + c1 t + ... + c6 t⁶`
+const VIRIDIS: [[f32; 3]; 7] = [
+    [0.277_727_33, 0.005_407_345, 0.334_099_8],
+    [0.105_093_04, 1.404_613_5, 1.384_590_2],
+    [-0.330_861_83, 0.214_847_56, 0.095_095_16],
+    [-4.634_230_4, -5.799_101, -19.332_441],
+    [6.228_27, 14.179_933, 56.690_55],
+    [4.776_385, -13.745_145, -65.353_03],
+    [-5.435_456, 4.645_852_6, 26.312_435],
+];
+
+const MAGMA: [[f32; 3]; 7] = [
+    [-0.002_136_485, -0.000_749_655, -0.005_386_128],
+    [0.251_660_54, 0.677_523_2, 2.494_026_6],
+    [8.353_717, -3.577_719_5, 0.314_467_9],
+    [-27.668_733, 14.264_731, -13.649_213],
+    [52.176_14, -27.943_606, 12.944_169],
+    [-50.768_524, 29.046_583, 4.234_153],
+    [18.655_705, -11.489_774, -5.601_961_5],
+];
+
+const INFERNO: [[f32; 3]; 7] = [
+    [0.000_218_940_37, 0.001_651_004_6, -0.019_480_899],
+    [0.106_513_42, 0.563_956_44, 3.932_712_4],
+    [11.602_493, -3.972_854, -15.942_394],
+    [-41.703_995, 17.436_4, 44.354_145],
+    [77.162_94, -33.402_36, -81.807_31],
+    [-71.319_43, 32.626_064, 73.209_52],
+    [25.131_126, -12.242_669, -23.070_324],
+];
+
+const PLASMA: [[f32; 3]; 7] = [
+    [0.058_732_344, 0.023_336_709, 0.543_340_2],
+    [2.176_514_6, 0.238_383_42, 0.753_960_45],
+    [-2.689_460_5, -7.455_851, 3.110_8],
+    [6.130_348, 42.346_188, -28.518_854],
+    [-11.107_436, -82.666_31, 60.139_847],
+    [10.023_066, 71.413_62, -54.072_186],
+    [-3.658_713_8, -22.931_534, 18.191_908],
+];
+
+/// Polynomial approximation published with Turbo by Google, 5th degree
+const TURBO: [[f32; 3]; 6] = [
+    [0.135_721_38, 0.091_402_61, 0.106_673_3],
+    [4.615_392_6, 2.194_188_4, 12.641_946],
+    [-42.660_323, 4.842_966_6, -60.582_05],
+    [132.131_08, -14.185_033, 110.362_77],
+    [-152.942_4, 4.277_299, -89.903_11],
+    [59.286_38, 2.829_566, 27.348_25],
+];
+
+/// `0xRRGGBB` evenly spaced samples, interpolated in Oklab
+const CIVIDIS: [u32; 10] = [
+    0x00204D, 0x00336F, 0x39486B, 0x575C6D, 0x707173, 0x8A8779, 0xA69D75, 0xC4B56C, 0xE4CF5B, 0xFFEA46,
+];
+
+/// Approximation of matplotlib twilight: white, blue, dark purple, red and back to white
+const TWILIGHT: [u32; 9] = [
+    0xE2D9E2, 0xA0B8CE, 0x5E80BF, 0x5D419D, 0x2F1436, 0x7D254B, 0xB4554B, 0xD29A84, 0xE2D9E2,
+];
+
+impl Colormap {
+    pub const ALL: [Colormap; 7] = [
+        Colormap::Viridis,
+        Colormap::Magma,
+        Colormap::Inferno,
+        Colormap::Plasma,
+        Colormap::Turbo,
+        Colormap::Cividis,
+        Colormap::Twilight,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Colormap::Viridis => "viridis",
+            Colormap::Magma => "magma",
+            Colormap::Inferno => "inferno",
+            Colormap::Plasma => "plasma",
+            Colormap::Turbo => "turbo",
+            Colormap::Cividis => "cividis",
+            Colormap::Twilight => "twilight",
+        }
+    }
+
+    /// Case insensitive
+    pub fn from_name(name: &str) -> Option<Colormap> {
+        Self::ALL.into_iter().find(|colormap| colormap.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// The first and the last colors are the same, values wrap around instead of being clamped
+    pub fn is_cyclic(self) -> bool {
+        self == Colormap::Twilight
+    }
+
+    /// Color at `t` in `[0, 1]`, clamped or wrapped for cyclic maps
+    pub fn sample(self, t: f32) -> RGB<u8> {
+        let t = if self.is_cyclic() { t.rem_euclid(1.0) } else { t.clamp(0.0, 1.0) };
+        match self {
+            Colormap::Viridis => polynomial(&VIRIDIS, t),
+            Colormap::Magma => polynomial(&MAGMA, t),
+            Colormap::Inferno => polynomial(&INFERNO, t),
+            Colormap::Plasma => polynomial(&PLASMA, t),
+            Colormap::Turbo => polynomial(&TURBO, t),
+            Colormap::Cividis => table(&CIVIDIS, t),
+            Colormap::Twilight => table(&TWILIGHT, t),
+        }
+    }
+
+    /// `size` precomputed colors, much faster than [`Self::sample()`] per pixel
+    pub fn to_lut(self, size: usize) -> GradientLut<RGB<u8>> {
+        let last = (size.max(1) - 1).max(1) as f32;
+        let samples: Vec<RGB<u8>> = (0..size.max(1)).map(|i| self.sample(i as f32 / last)).collect();
+        let wrap = if self.is_cyclic() { WrapMode::Repeat } else { WrapMode::Clamp };
+        Gradient::even(&samples).with_wrap(wrap).to_lut(samples.len())
+    }
+
+    /// Map `values` from `[min, max]` to `0x00RRGGBB` pixels, e.g. for [`super::super::framebuffer::Framebuffer::from_pixels()`]
+    pub fn colorize(self, values: &[f32], min: f32, max: f32) -> Vec<u32> {
+        let lut = self.to_lut(256);
+        let range = if max > min { max - min } else { 1.0 };
+        values.iter().map(|value| lut.sample((value - min) / range).to_pixel()).collect()
+    }
+}
+
+impl fmt::Display for Colormap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnknownColormap(pub String);
+
+impl fmt::Display for UnknownColormap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown colormap '{}'", self.0)
+    }
+}
+
+impl std::error::Error for UnknownColormap {}
+
+impl FromStr for Colormap {
+    type Err = UnknownColormap;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Colormap::from_name(s).ok_or_else(|| UnknownColormap(s.to_string()))
+    }
+}
+
+fn polynomial<const N: usize>(coefficients: &[[f32; 3]; N], t: f32) -> RGB<u8> {
+    // Horner's method
+    let mut color = [0.0f32; 3];
+    for c in coefficients.iter().rev() {
+        for (channel, coefficient) in color.iter_mut().zip(c) {
+            *channel = *channel * t + coefficient;
+        }
+    }
+    RGB::new(color[0], color[1], color[2]).to_u8()
+}
+
+fn table(colors: &[u32], t: f32) -> RGB<u8> {
+    let position = t * (colors.len() - 1) as f32;
+    let index = (position as usize).min(colors.len() - 2);
+    let (from, to) = (RGB::from_pixel(colors[index]), RGB::from_pixel(colors[index + 1]));
+    ColorSpace::Oklab.lerp(from, to, position - index as f32)
+}
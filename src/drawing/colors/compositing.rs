@@ -0,0 +1,107 @@
+use super::RGBA;
+
+/// Porter–Duff operators, see <https://www.w3.org/TR/compositing-1/#porterduffcompositingoperators>.
+///
+/// Source is the new layer, destination is what was drawn before
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum CompositeOp {
+    Clear,
+    Source,
+    Destination,
+    /// Normal alpha blending
+    #[default]
+    SourceOver,
+    DestinationOver,
+    SourceIn,
+    DestinationIn,
+    SourceOut,
+    DestinationOut,
+    SourceAtop,
+    DestinationAtop,
+    Xor,
+    /// Sum of source and destination, clamped
+    Plus,
+}
+
+impl CompositeOp {
+    /// Factors (Fa, Fb) for source and destination: `result = source * Fa + destination * Fb`
+    #[inline(always)]
+    pub fn factors(self, source_alpha: f32, destination_alpha: f32) -> (f32, f32) {
+        let (sa, da) = (source_alpha, destination_alpha);
+        match self {
+            CompositeOp::Clear => (0.0, 0.0),
+            CompositeOp::Source => (1.0, 0.0),
+            CompositeOp::Destination => (0.0, 1.0),
+            CompositeOp::SourceOver => (1.0, 1.0 - sa),
+            CompositeOp::DestinationOver => (1.0 - da, 1.0),
+            CompositeOp::SourceIn => (da, 0.0),
+            CompositeOp::DestinationIn => (0.0, sa),
+            CompositeOp::SourceOut => (1.0 - da, 0.0),
+            CompositeOp::DestinationOut => (0.0, 1.0 - sa),
+            CompositeOp::SourceAtop => (da, 1.0 - sa),
+            CompositeOp::DestinationAtop => (1.0 - da, sa),
+            CompositeOp::Xor => (1.0 - da, 1.0 - sa),
+            CompositeOp::Plus => (1.0, 1.0),
+        }
+    }
+}
+
+/// Multiply color components by alpha
+#[inline(always)]
+pub fn premultiply(color: RGBA<f32>) -> RGBA<f32> {
+    RGBA::new(color.r * color.a, color.g * color.a, color.b * color.a, color.a)
+}
+
+/// Inverse of [`premultiply()`]. Fully transparent color becomes transparent black
+#[inline(always)]
+pub fn unpremultiply(color: RGBA<f32>) -> RGBA<f32> {
+    if color.a <= 0.0 {
+        return RGBA::new(0.0, 0.0, 0.0, 0.0);
+    }
+    RGBA::new(color.r / color.a, color.g / color.a, color.b / color.a, color.a)
+}
+
+/// Composite <b>premultiplied</b> colors with components in `[0, 1]`
+#[inline(always)]
+pub fn composite_premultiplied(source: RGBA<f32>, destination: RGBA<f32>, op: CompositeOp) -> RGBA<f32> {
+    let (fa, fb) = op.factors(source.a, destination.a);
+    let blend = |s: f32, d: f32| (s * fa + d * fb).min(1.0);
+    RGBA::new(
+        blend(source.r, destination.r),
+        blend(source.g, destination.g),
+        blend(source.b, destination.b),
+        blend(source.a, destination.a),
+    )
+}
+
+/// Composite straight alpha colors
+pub fn composite(source: RGBA<u8>, destination: RGBA<u8>, op: CompositeOp) -> RGBA<u8> {
+    let result = composite_premultiplied(premultiply(source.to_f32()), premultiply(destination.to_f32()), op);
+    unpremultiply(result).to_u8()
+}
+
+/// Composite packed straight alpha `0xAARRGGBB` pixels. `opacity` in `[0, 1]` multiplies source alpha
+#[inline(always)]
+pub fn composite_argb(source: u32, destination: u32, op: CompositeOp, opacity: f32) -> u32 {
+    let mut source = premultiply(RGBA::from_argb(source).to_f32());
+    source = RGBA::new(source.r * opacity, source.g * opacity, source.b * opacity, source.a * opacity);
+    let destination = premultiply(RGBA::from_argb(destination).to_f32());
+    unpremultiply(composite_premultiplied(source, destination, op)).to_u8().to_argb()
+}
+
+impl RGBA<u8> {
+    /// Straight alpha to premultiplied
+    pub fn premultiplied(self) -> RGBA<u8> {
+        premultiply(self.to_f32()).to_u8()
+    }
+
+    /// Premultiplied alpha to straight
+    pub fn unpremultiplied(self) -> RGBA<u8> {
+        unpremultiply(self.to_f32()).to_u8()
+    }
+
+    /// `self` drawn over `destination` ([`CompositeOp::SourceOver`])
+    pub fn over(self, destination: RGBA<u8>) -> RGBA<u8> {
+        composite(self, destination, CompositeOp::SourceOver)
+    }
+}
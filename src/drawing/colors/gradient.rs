@@ -0,0 +1,222 @@
+use super::spaces::ColorSpace;
+use super::RGB;
+
+/// Value which can be placed in a [`Gradient`]
+pub trait Lerp: Copy {
+    /// Interpolate from `self` to `other`, `t` in `[0, 1]`. `space` is used by colors only
+    fn lerp(self, other: Self, t: f32, space: ColorSpace) -> Self;
+}
+
+impl Lerp for f32 {
+    #[inline(always)]
+    fn lerp(self, other: Self, t: f32, _space: ColorSpace) -> Self {
+        (1.0 - t) * self + t * other
+    }
+}
+
+impl Lerp for RGB<u8> {
+    #[inline(always)]
+    fn lerp(self, other: Self, t: f32, space: ColorSpace) -> Self {
+        space.lerp(self, other, t)
+    }
+}
+
+/// Curve of the transition between two neighbour stops
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Hold the first value until the next stop
+    Step,
+    /// Hermite `3t² - 2t³`
+    Smooth,
+    /// Quadratic, slow start
+    EaseIn,
+    /// Quadratic, slow end
+    EaseOut,
+    /// Cubic, slow start and end
+    EaseInOut,
+    /// `t ^ exponent`
+    Power(f32),
+}
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::Step => 0.0,
+            Easing::Smooth => t * t * (3.0 - 2.0 * t),
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::Power(exponent) => t.powf(exponent),
+        }
+    }
+}
+
+/// What happens with positions outside of `[0, 1]`
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum WrapMode {
+    /// Use the first or the last stop
+    #[default]
+    Clamp,
+    /// `1.25` is the same as `0.25`
+    Repeat,
+    /// `1.25` is the same as `0.75`
+    Mirror,
+}
+
+impl WrapMode {
+    /// Map any position to `[0, 1]`
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            WrapMode::Clamp => t.clamp(0.0, 1.0),
+            WrapMode::Repeat => t.rem_euclid(1.0),
+            WrapMode::Mirror => {
+                let t = t.rem_euclid(2.0);
+                if t > 1.0 { 2.0 - t } else { t }
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GradientStop<T> {
+    /// Position in `[0, 1]`
+    pub position: f32,
+    pub value: T,
+    /// Easing of the segment from this stop to the next one
+    pub easing: Easing,
+}
+
+impl<T> GradientStop<T> {
+    pub const fn new(position: f32, value: T) -> Self {
+        Self { position, value, easing: Easing::Linear }
+    }
+
+    pub const fn with_easing(position: f32, value: T, easing: Easing) -> Self {
+        Self { position, value, easing }
+    }
+}
+
+/// Gradient of colors or floats with arbitrary stops.
+///
+/// Example:
+/// ```
+/// let gradient = Gradient::new(vec![
+///     GradientStop::new(0.0, RGB::new(0, 0, 0)),
+///     GradientStop::with_easing(0.2, RGB::new(255, 0, 0), Easing::Smooth),
+///     GradientStop::new(1.0, RGB::new(255, 255, 255)),
+/// ])
+/// .with_space(ColorSpace::Oklab)
+/// .with_wrap(WrapMode::Mirror);
+/// let color = gradient.sample(0.5);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient<T> {
+    stops: Vec<GradientStop<T>>,
+    pub wrap: WrapMode,
+    /// Interpolation space, used by colors only
+    pub space: ColorSpace,
+}
+
+impl<T: Lerp> Gradient<T> {
+    /// Stops are sorted by position
+    ///
+    /// # Panics
+    /// If `stops` is empty
+    pub fn new(mut stops: Vec<GradientStop<T>>) -> Self {
+        assert!(!stops.is_empty(), "Gradient requires at least one stop");
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        Self { stops, wrap: WrapMode::Clamp, space: ColorSpace::Srgb }
+    }
+
+    /// Evenly spaced values from 0 to 1
+    ///
+    /// # Panics
+    /// If `values` is empty
+    pub fn even(values: &[T]) -> Self {
+        let segments = values.len().saturating_sub(1).max(1) as f32;
+        let stops = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| GradientStop::new(i as f32 / segments, *value))
+            .collect();
+        Self::new(stops)
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_space(mut self, space: ColorSpace) -> Self {
+        self.space = space;
+        self
+    }
+
+    /// Set easing for all segments
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        for stop in &mut self.stops {
+            stop.easing = easing;
+        }
+        self
+    }
+
+    pub fn stops(&self) -> &[GradientStop<T>] {
+        &self.stops
+    }
+
+    pub fn sample(&self, position: f32) -> T {
+        let t = self.wrap.apply(position);
+        let stops = &self.stops;
+
+        // First stop with position greater than `t`
+        let next = stops.partition_point(|stop| stop.position <= t);
+        if next == 0 {
+            return stops[0].value;
+        }
+        if next == stops.len() {
+            return stops[next - 1].value;
+        }
+
+        let (from, to) = (&stops[next - 1], &stops[next]);
+        let local = (t - from.position) / (to.position - from.position);
+        from.value.lerp(to.value, from.easing.apply(local), self.space)
+    }
+
+    /// Precompute `size` samples for fast lookups, e.g. per pixel in fractals
+    pub fn to_lut(&self, size: usize) -> GradientLut<T> {
+        let size = size.max(1);
+        let last = (size - 1).max(1) as f32;
+        let values = (0..size).map(|i| self.sample(i as f32 / last)).collect();
+        GradientLut { values, wrap: self.wrap }
+    }
+}
+
+/// Sampled [`Gradient`], see [`Gradient::to_lut()`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct GradientLut<T> {
+    values: Vec<T>,
+    pub wrap: WrapMode,
+}
+
+impl<T: Copy> GradientLut<T> {
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Nearest precomputed value
+    #[inline(always)]
+    pub fn sample(&self, position: f32) -> T {
+        let last = self.values.len() - 1;
+        let index = (self.wrap.apply(position) * last as f32).round() as usize;
+        self.values[index.min(last)]
+    }
+}
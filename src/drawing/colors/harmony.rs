@@ -0,0 +1,167 @@
+use super::spaces::{normalize_hue, Oklch};
+use super::{contrast_ratio, RGB};
+
+/// Tolerance for sRGB components of gamut mapped colors
+const GAMUT_EPSILON: f32 = 1e-4;
+
+/// Classic color wheel schemes. Hues are rotated in OKLCH, so the colors keep the perceived lightness of the base
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Harmony {
+    /// Base and the opposite hue
+    #[default]
+    Complementary,
+    /// Base and neighbours 30° apart
+    Analogous,
+    /// Three hues 120° apart
+    Triadic,
+    /// Four hues 90° apart
+    Tetradic,
+    /// Base and two neighbours of the opposite hue
+    SplitComplementary,
+}
+
+impl Harmony {
+    /// Hue offsets in degrees, the first one is always the base
+    pub fn hue_offsets(self) -> &'static [f32] {
+        match self {
+            Harmony::Complementary => &[0.0, 180.0],
+            Harmony::Analogous => &[0.0, -30.0, 30.0],
+            Harmony::Triadic => &[0.0, 120.0, 240.0],
+            Harmony::Tetradic => &[0.0, 90.0, 180.0, 270.0],
+            Harmony::SplitComplementary => &[0.0, 150.0, 210.0],
+        }
+    }
+
+    /// Scheme colors, the first one is `base`
+    pub fn colors(self, base: RGB<u8>) -> Vec<RGB<u8>> {
+        self.hue_offsets().iter().map(|&offset| base.rotate_hue(offset)).collect()
+    }
+}
+
+impl RGB<u8> {
+    /// Rotate OKLCH hue by `degrees`, lightness and chroma are kept when they fit into sRGB
+    pub fn rotate_hue(self, degrees: f32) -> RGB<u8> {
+        if degrees == 0.0 {
+            return self;
+        }
+        let lch = self.to_oklch();
+        gamut_map(Oklch { h: normalize_hue(lch.h + degrees), ..lch })
+    }
+
+    pub fn complementary(self) -> RGB<u8> {
+        self.rotate_hue(180.0)
+    }
+}
+
+/// Oklch to sRGB, chroma is reduced until the color fits into the gamut, lightness and hue are kept
+pub fn gamut_map(lch: Oklch) -> RGB<u8> {
+    let in_gamut = |chroma: f32| {
+        let RGB { r, g, b } = RGB::<f32>::from_oklch(Oklch { c: chroma, ..lch });
+        [r, g, b].iter().all(|c| (-GAMUT_EPSILON..=1.0 + GAMUT_EPSILON).contains(c))
+    };
+    if in_gamut(lch.c) {
+        return RGB::<u8>::from_oklch(lch);
+    }
+
+    let (mut low, mut high) = (0.0, lch.c);
+    for _ in 0..20 {
+        let middle = (low + high) / 2.0;
+        if in_gamut(middle) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    RGB::<u8>::from_oklch(Oklch { c: low, ..lch })
+}
+
+/// `count` colors from `base` towards white, the first one is `base`, white is not included
+pub fn tints(base: RGB<u8>, count: usize) -> Vec<RGB<u8>> {
+    toward(base, count, |lch, t| Oklch { l: lch.l + (1.0 - lch.l) * t, c: lch.c * (1.0 - t), h: lch.h })
+}
+
+/// `count` colors from `base` towards black, the first one is `base`, black is not included
+pub fn shades(base: RGB<u8>, count: usize) -> Vec<RGB<u8>> {
+    toward(base, count, |lch, t| Oklch { l: lch.l * (1.0 - t), c: lch.c * (1.0 - t), h: lch.h })
+}
+
+/// `count` colors from `base` towards grey of the same lightness, the first one is `base`
+pub fn tones(base: RGB<u8>, count: usize) -> Vec<RGB<u8>> {
+    toward(base, count, |lch, t| Oklch { c: lch.c * (1.0 - t), ..lch })
+}
+
+fn toward(base: RGB<u8>, count: usize, step: impl Fn(Oklch, f32) -> Oklch) -> Vec<RGB<u8>> {
+    let lch = base.to_oklch();
+    (0..count).map(|i| gamut_map(step(lch, i as f32 / count as f32))).collect()
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum ThemeMode {
+    /// Bright colors on a dark background
+    #[default]
+    Dark,
+    Light,
+}
+
+/// Colors for a scene derived from a single base color.
+/// Primary and accents have at least [`Theme::MIN_CONTRAST`] against the background.
+///
+/// Example:
+/// ```
+/// let theme = Theme::from_base(RGB::new(255, 140, 0), Harmony::Triadic, ThemeMode::Dark);
+/// let galaxy = Galaxy::new(x, y, width, height, theme.color(index));
+/// let params = SpiralParams { color: SpiralColor::from_theme(&theme), ..Default::default() };
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Theme {
+    pub background: RGB<u8>,
+    pub primary: RGB<u8>,
+    pub accents: Vec<RGB<u8>>,
+}
+
+impl Theme {
+    /// WCAG ratio for graphical objects
+    pub const MIN_CONTRAST: f32 = 3.0;
+
+    pub fn from_base(base: RGB<u8>, harmony: Harmony, mode: ThemeMode) -> Theme {
+        let lch = base.to_oklch();
+        let background = match mode {
+            ThemeMode::Dark => gamut_map(Oklch { l: 0.18, c: lch.c.min(0.03), h: lch.h }),
+            ThemeMode::Light => gamut_map(Oklch { l: 0.97, c: lch.c.min(0.015), h: lch.h }),
+        };
+
+        let mut colors = harmony.colors(base).into_iter().map(|c| with_contrast(c, background, Self::MIN_CONTRAST));
+        let primary = colors.next().unwrap_or(base);
+        Theme { background, primary, accents: colors.collect() }
+    }
+
+    /// Primary followed by accents, e.g. for [`super::gradient::Gradient::even()`]
+    pub fn colors(&self) -> Vec<RGB<u8>> {
+        std::iter::once(self.primary).chain(self.accents.iter().copied()).collect()
+    }
+
+    /// COLORREF of [`Self::colors()`] at `index`, wraps around. Handy for a sequence of scene objects
+    pub fn color(&self, index: usize) -> u32 {
+        let count = self.accents.len() + 1;
+        match index % count {
+            0 => self.primary.to_colorref(),
+            i => self.accents[i - 1].to_colorref(),
+        }
+    }
+
+    pub fn background_colorref(&self) -> u32 {
+        self.background.to_colorref()
+    }
+}
+
+/// Move OKLCH lightness of `color` away from `background` until the contrast is reached or lightness runs out
+fn with_contrast(color: RGB<u8>, background: RGB<u8>, ratio: f32) -> RGB<u8> {
+    let mut lch = color.to_oklch();
+    let direction = if background.relative_luminance() < 0.18 { 0.02 } else { -0.02 };
+    let mut result = color;
+    while contrast_ratio(result, background) < ratio && (0.0..=1.0).contains(&(lch.l + direction)) {
+        lch.l += direction;
+        result = gamut_map(lch);
+    }
+    result
+}
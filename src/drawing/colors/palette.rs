@@ -0,0 +1,379 @@
+use std::fmt;
+use std::path::Path;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::spaces::{linear_to_srgb, ColorSpace};
+use super::{interpolate_colors_in, mix_colors_in, RGB};
+
+#[derive(Debug)]
+pub enum PaletteError {
+    Io(std::io::Error),
+    /// Not a GIMP, Adobe Swatch Exchange or Paint.NET palette
+    UnknownFormat,
+    /// Malformed line of a text palette, 1-based
+    InvalidLine(usize),
+    /// Malformed `.ase` file
+    InvalidAse(&'static str),
+    /// File has no colors
+    Empty,
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaletteError::Io(error) => write!(f, "Could not read the palette file: {}", error),
+            PaletteError::UnknownFormat => write!(f, "Unknown palette format"),
+            PaletteError::InvalidLine(line) => write!(f, "Invalid palette entry on line {}", line),
+            PaletteError::InvalidAse(reason) => write!(f, "Invalid Adobe Swatch Exchange file: {}", reason),
+            PaletteError::Empty => write!(f, "Palette has no colors"),
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {}
+
+impl From<std::io::Error> for PaletteError {
+    fn from(error: std::io::Error) -> Self {
+        PaletteError::Io(error)
+    }
+}
+
+/// List of colors, usually made by a designer. Derefs to `[RGB<u8>]`,
+/// so it can be passed to [`super::interpolate_colors()`] and [`super::mix_colors()`] directly.
+///
+/// Supported files:
+///
+/// <b>GIMP</b> `.gpl` - `GIMP Palette` header and `R G B name` lines
+///
+/// <b>Adobe Swatch Exchange</b> `.ase` - RGB, CMYK, LAB and gray swatches, groups are flattened
+///
+/// <b>Paint.NET</b> `.txt` - `AARRGGBB` hex lines, alpha is ignored
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Palette {
+    name: Option<String>,
+    colors: Vec<RGB<u8>>,
+    color_names: Vec<Option<String>>,
+}
+
+impl Palette {
+    pub fn new(colors: Vec<RGB<u8>>) -> Self {
+        let color_names = vec![None; colors.len()];
+        Self { name: None, colors, color_names }
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn push(&mut self, color: RGB<u8>, name: Option<String>) {
+        self.colors.push(color);
+        self.color_names.push(name);
+    }
+
+    /// Format is detected from the content, not from the extension
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Palette, PaletteError> {
+        Palette::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Palette, PaletteError> {
+        if data.starts_with(b"ASEF") {
+            return Palette::parse_ase(data);
+        }
+
+        let text = std::str::from_utf8(data).map_err(|_| PaletteError::UnknownFormat)?;
+        let text = text.trim_start_matches('\u{feff}');
+        if text.trim_start().starts_with("GIMP Palette") {
+            Palette::parse_gpl(text)
+        } else if is_paint_net(text) {
+            Palette::parse_paint_net(text)
+        } else {
+            Err(PaletteError::UnknownFormat)
+        }
+    }
+
+    /// GIMP `.gpl` palette
+    pub fn parse_gpl(text: &str) -> Result<Palette, PaletteError> {
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, header)) if header.trim() == "GIMP Palette" => {}
+            _ => return Err(PaletteError::UnknownFormat),
+        }
+
+        let mut palette = Palette::default();
+        for (index, line) in lines {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix("Name:") {
+                palette.name = Some(name.trim().to_string());
+                continue;
+            }
+            if line.starts_with("Columns:") {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let mut component = || {
+                let value = parts.next().and_then(|c| c.parse::<u8>().ok());
+                value.ok_or(PaletteError::InvalidLine(index + 1))
+            };
+            let color = RGB::new(component()?, component()?, component()?);
+            let name = parts.collect::<Vec<_>>().join(" ");
+            palette.push(color, (!name.is_empty() && name != "Untitled").then_some(name));
+        }
+        palette.non_empty()
+    }
+
+    /// Paint.NET palette, `;` starts a comment.
+    /// Text whose first entry is not a hex color is [`PaletteError::UnknownFormat`]
+    pub fn parse_paint_net(text: &str) -> Result<Palette, PaletteError> {
+        if !is_paint_net(text) {
+            return Err(PaletteError::UnknownFormat);
+        }
+
+        let mut palette = Palette::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = paint_net_entry(line);
+            if line.is_empty() {
+                continue;
+            }
+            if !is_hex_color(line) {
+                return Err(PaletteError::InvalidLine(index + 1));
+            }
+            let pixel = u32::from_str_radix(line, 16).map_err(|_| PaletteError::InvalidLine(index + 1))?;
+            palette.push(RGB::from_pixel(pixel), None);
+        }
+        palette.non_empty()
+    }
+
+    /// Adobe Swatch Exchange `.ase` palette
+    pub fn parse_ase(data: &[u8]) -> Result<Palette, PaletteError> {
+        let mut reader = AseReader { data, offset: 0 };
+        if reader.bytes(4)? != b"ASEF" {
+            return Err(PaletteError::UnknownFormat);
+        }
+        reader.bytes(4)?; // Version
+        let block_count = reader.u32()?;
+
+        let mut palette = Palette::default();
+        for _ in 0..block_count {
+            let block_type = reader.u16()?;
+            let length = reader.u32()? as usize;
+            let mut block = AseReader { data: reader.bytes(length)?, offset: 0 };
+
+            // Group start and end blocks only carry the group name
+            if block_type != 0x0001 {
+                continue;
+            }
+            let name = block.string()?;
+            let model = block.bytes(4)?;
+            let color = match model {
+                b"RGB " => {
+                    let (r, g, b) = (block.f32()?, block.f32()?, block.f32()?);
+                    RGB::new(r, g, b).to_u8()
+                }
+                b"CMYK" => {
+                    let (c, m, y, k) = (block.f32()?, block.f32()?, block.f32()?, block.f32()?);
+                    RGB::new((1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k)).to_u8()
+                }
+                b"LAB " => {
+                    let (l, a, b) = (block.f32()?, block.f32()?, block.f32()?);
+                    lab_to_rgb(l * 100.0, a, b)
+                }
+                b"Gray" => {
+                    let v = block.f32()?;
+                    RGB::new(v, v, v).to_u8()
+                }
+                _ => return Err(PaletteError::InvalidAse("unknown color model")),
+            };
+            palette.push(color, (!name.is_empty()).then_some(name));
+        }
+        palette.non_empty()
+    }
+
+    fn non_empty(self) -> Result<Palette, PaletteError> {
+        if self.colors.is_empty() {
+            return Err(PaletteError::Empty);
+        }
+        Ok(self)
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn colors(&self) -> &[RGB<u8>] {
+        &self.colors
+    }
+
+    /// Name of the color at `index`, if the file had one
+    pub fn color_name(&self, index: usize) -> Option<&str> {
+        self.color_names.get(index)?.as_deref()
+    }
+
+    /// Index of the closest color by squared sRGB distance. Panics if the palette is empty
+    pub fn nearest_index(&self, color: RGB<u8>) -> usize {
+        let distance = |c: &RGB<u8>| {
+            let (r, g, b) = (c.r as i32 - color.r as i32, c.g as i32 - color.g as i32, c.b as i32 - color.b as i32);
+            r * r + g * g + b * b
+        };
+        let (index, _) = self.colors.iter().enumerate().min_by_key(|(_, c)| distance(c)).expect("Palette is empty");
+        index
+    }
+
+    /// Closest color of the palette, see [`Self::nearest_index()`]
+    pub fn nearest(&self, color: RGB<u8>) -> RGB<u8> {
+        self.colors[self.nearest_index(color)]
+    }
+
+    /// Color with the given name, case insensitive
+    pub fn find(&self, name: &str) -> Option<RGB<u8>> {
+        let index = self
+            .color_names
+            .iter()
+            .position(|n| n.as_deref().is_some_and(|n| n.eq_ignore_ascii_case(name)))?;
+        Some(self.colors[index])
+    }
+
+    /// See [`super::interpolate_colors()`], returns COLORREF. Black for an empty palette
+    pub fn interpolate(&self, weight: f32, space: ColorSpace) -> u32 {
+        interpolate_colors_in(&self.colors, weight, space)
+    }
+
+    /// See [`super::mix_colors()`]
+    pub fn mix(&self, proportions: &[f32], space: ColorSpace) -> RGB<u8> {
+        mix_colors_in(&self.colors, proportions, space)
+    }
+
+    /// Random color of the palette as COLORREF, like [`super::random_color()`]. Panics if the palette is empty
+    pub fn random_color(&self) -> u32 {
+        self.choose(&mut rand::thread_rng()).to_colorref()
+    }
+
+    /// Random color of the palette from `rng`. Panics if the palette is empty
+    pub fn choose<R: Rng + ?Sized>(&self, rng: &mut R) -> RGB<u8> {
+        *self.colors.choose(rng).expect("Palette is empty")
+    }
+}
+
+impl std::ops::Deref for Palette {
+    type Target = [RGB<u8>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.colors
+    }
+}
+
+impl From<Vec<RGB<u8>>> for Palette {
+    fn from(colors: Vec<RGB<u8>>) -> Self {
+        Palette::new(colors)
+    }
+}
+
+/// Line without the `;` comment
+fn paint_net_entry(line: &str) -> &str {
+    line.split(';').next().unwrap_or_default().trim()
+}
+
+/// `RRGGBB` or `AARRGGBB`
+fn is_hex_color(entry: &str) -> bool {
+    (entry.len() == 6 || entry.len() == 8) && entry.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// The first entry decides, later malformed lines are reported as [`PaletteError::InvalidLine`].
+/// Text of only comments is an empty Paint.NET palette
+fn is_paint_net(text: &str) -> bool {
+    text.lines().map(paint_net_entry).find(|entry| !entry.is_empty()).is_none_or(is_hex_color)
+}
+
+/// Big endian reader of `.ase` data
+struct AseReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> AseReader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], PaletteError> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + count)
+            .ok_or(PaletteError::InvalidAse("unexpected end of data"))?;
+        self.offset += count;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, PaletteError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, PaletteError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f32, PaletteError> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    /// Length in UTF-16 units including the terminating zero, then UTF-16BE text
+    fn string(&mut self) -> Result<String, PaletteError> {
+        let length = self.u16()? as usize;
+        let units = (0..length).map(|_| self.u16()).collect::<Result<Vec<u16>, _>>()?;
+        let units = units.strip_suffix(&[0]).unwrap_or(&units);
+        String::from_utf16(units).map_err(|_| PaletteError::InvalidAse("invalid color name"))
+    }
+}
+
+/// CIE L*a*b* (D50, as used by Adobe) to sRGB
+fn lab_to_rgb(l: f32, a: f32, b: f32) -> RGB<u8> {
+    const EPSILON: f32 = 6.0 / 29.0;
+    let inverse = |t: f32| {
+        if t > EPSILON { t * t * t } else { 3.0 * EPSILON * EPSILON * (t - 4.0 / 29.0) }
+    };
+
+    let fy = (l + 16.0) / 116.0;
+    let x = 0.96422 * inverse(fy + a / 500.0);
+    let y = inverse(fy);
+    let z = 0.82521 * inverse(fy - b / 200.0);
+
+    // Bradford adapted XYZ D50 to linear sRGB
+    let r = 3.133856 * x - 1.6168667 * y - 0.4906146 * z;
+    let g = -0.9787684 * x + 1.9161415 * y + 0.033454 * z;
+    let b = 0.0719453 * x - 0.2289914 * y + 1.4052427 * z;
+    RGB::new(linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b)).to_u8()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_formats() {
+        let gpl = "GIMP Palette\nName: Test\n255 0 0 Red\n0 0 255\n";
+        assert_eq!(Palette::from_bytes(gpl.as_bytes()).unwrap().colors(), [RGB::new(255, 0, 0), RGB::new(0, 0, 255)]);
+        let paint_net = "; paint.net Palette File\nFFFF0000\n00ff00\n";
+        let colors = [RGB::new(255, 0, 0), RGB::new(0, 255, 0)];
+        assert_eq!(Palette::from_bytes(paint_net.as_bytes()).unwrap().colors(), colors);
+    }
+
+    #[test]
+    fn unknown_text_is_not_an_invalid_line() {
+        for text in ["Hello world\nFF0000", "<svg></svg>", "{\"colors\": []}"] {
+            assert!(matches!(Palette::from_bytes(text.as_bytes()), Err(PaletteError::UnknownFormat)), "{}", text);
+        }
+        assert!(matches!(Palette::from_bytes(&[0xFF, 0xFE, 0x00]), Err(PaletteError::UnknownFormat)));
+    }
+
+    #[test]
+    fn malformed_entries_are_invalid_lines() {
+        let paint_net = "; comment\nFF0000\nnot a color\n";
+        assert!(matches!(Palette::from_bytes(paint_net.as_bytes()), Err(PaletteError::InvalidLine(3))));
+        let gpl = "GIMP Palette\n255 0\n";
+        assert!(matches!(Palette::from_bytes(gpl.as_bytes()), Err(PaletteError::InvalidLine(2))));
+    }
+}
@@ -0,0 +1,459 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::spaces::Hsl;
+use super::{RGB, RGBA};
+
+/// Why a color string could not be parsed
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseColorError {
+    Empty,
+    /// `#` notation must have 3, 4, 6 or 8 digits
+    InvalidHexLength(usize),
+    InvalidHexDigit(char),
+    /// Not a CSS named color
+    UnknownName(String),
+    /// Only `rgb()`, `rgba()`, `hsl()` and `hsla()` are supported
+    UnknownFunction(String),
+    MissingParenthesis,
+    /// Empty argument between commas, commas mixed with spaces or `/`, or alpha without `/` in space syntax
+    InvalidSeparator,
+    /// Function takes 3 components and optional alpha
+    WrongArgumentCount { function: String, found: usize },
+    InvalidNumber(String),
+    /// Translucent color can not be parsed as [`RGB`]
+    UnexpectedAlpha,
+}
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseColorError::Empty => write!(f, "empty color string"),
+            ParseColorError::InvalidHexLength(length) => {
+                write!(f, "hex color must have 3, 4, 6 or 8 digits, found {}", length)
+            }
+            ParseColorError::InvalidHexDigit(c) => write!(f, "invalid hex digit '{}'", c),
+            ParseColorError::UnknownName(name) => write!(f, "unknown color name '{}'", name),
+            ParseColorError::UnknownFunction(name) => write!(f, "unknown color function '{}()'", name),
+            ParseColorError::MissingParenthesis => write!(f, "color function is missing a parenthesis"),
+            ParseColorError::InvalidSeparator => write!(f, "color function arguments are not separated consistently"),
+            ParseColorError::WrongArgumentCount { function, found } => {
+                write!(f, "{}() takes 3 or 4 arguments, found {}", function, found)
+            }
+            ParseColorError::InvalidNumber(value) => write!(f, "invalid number '{}'", value),
+            ParseColorError::UnexpectedAlpha => write!(f, "color with alpha can not be parsed as RGB"),
+        }
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+/// Parse `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, CSS named colors,
+/// `rgb()`/`rgba()` and `hsl()`/`hsla()` in both comma and space separated syntax:
+/// ```
+/// let color: RGBA<u8> = "rgb(255 128 0 / 50%)".parse()?;
+/// let color: RGBA<u8> = "hsla(120, 100%, 50%, 0.5)".parse()?;
+/// let color: RGB<u8> = "cornflowerblue".parse()?;
+/// ```
+impl FromStr for RGBA<u8> {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        if s.is_empty() {
+            return Err(ParseColorError::Empty);
+        }
+
+        if let Some(hex) = s.strip_prefix('#') {
+            return parse_hex(hex);
+        }
+        if let Some(open) = s.find('(') {
+            return parse_function(&s[..open], &s[open + 1..]);
+        }
+        if s == "transparent" {
+            return Ok(RGBA::new(0, 0, 0, 0));
+        }
+        named_color(&s).map(|color| color.with_alpha(255)).ok_or(ParseColorError::UnknownName(s))
+    }
+}
+
+/// Same syntax as [`RGBA`], but alpha must be opaque
+impl FromStr for RGB<u8> {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let color: RGBA<u8> = s.parse()?;
+        if color.a != 255 {
+            return Err(ParseColorError::UnexpectedAlpha);
+        }
+        Ok(color.rgb())
+    }
+}
+
+/// `#rrggbb`
+impl fmt::Display for RGB<u8> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// `#rrggbbaa`
+impl fmt::Display for RGBA<u8> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a)
+    }
+}
+
+fn parse_hex(hex: &str) -> Result<RGBA<u8>, ParseColorError> {
+    let digits = hex
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8).ok_or(ParseColorError::InvalidHexDigit(c)))
+        .collect::<Result<Vec<u8>, _>>()?;
+
+    match digits.len() {
+        3 | 4 => {
+            let component = |i: usize| digits.get(i).map_or(255, |d| d * 17);
+            Ok(RGBA::new(component(0), component(1), component(2), component(3)))
+        }
+        6 | 8 => {
+            let component = |i: usize| digits.get(i * 2).map_or(255, |d| d * 16 + digits[i * 2 + 1]);
+            Ok(RGBA::new(component(0), component(1), component(2), component(3)))
+        }
+        length => Err(ParseColorError::InvalidHexLength(length)),
+    }
+}
+
+fn parse_function(name: &str, rest: &str) -> Result<RGBA<u8>, ParseColorError> {
+    let name = name.trim();
+    let arguments = rest.strip_suffix(')').ok_or(ParseColorError::MissingParenthesis)?;
+    let arguments = split_arguments(arguments)?;
+    if arguments.len() != 3 && arguments.len() != 4 {
+        return Err(ParseColorError::WrongArgumentCount { function: name.to_string(), found: arguments.len() });
+    }
+
+    let alpha = match arguments.get(3) {
+        Some(alpha) => (parse_fraction(alpha)? * 255.0).round() as u8,
+        None => 255,
+    };
+
+    match name {
+        "rgb" | "rgba" => {
+            let component = |value: &str| -> Result<u8, ParseColorError> {
+                let value = match value.strip_suffix('%') {
+                    Some(percent) => parse_number(percent)? * 2.55,
+                    None => parse_number(value)?,
+                };
+                Ok(value.clamp(0.0, 255.0).round() as u8)
+            };
+            let (r, g, b) = (component(arguments[0])?, component(arguments[1])?, component(arguments[2])?);
+            Ok(RGBA::new(r, g, b, alpha))
+        }
+        "hsl" | "hsla" => {
+            let hue = parse_hue(arguments[0])?;
+            let percent = |value: &str| -> Result<f32, ParseColorError> {
+                let value = value.strip_suffix('%').unwrap_or(value);
+                Ok((parse_number(value)? / 100.0).clamp(0.0, 1.0))
+            };
+            let hsl = Hsl { h: hue, s: percent(arguments[1])?, l: percent(arguments[2])? };
+            Ok(RGB::<u8>::from_hsl(hsl).with_alpha(alpha))
+        }
+        _ => Err(ParseColorError::UnknownFunction(name.to_string())),
+    }
+}
+
+/// `1, 2, 3, 0.5` or `1 2 3 / 0.5`, the alpha of the space syntax must follow the components
+fn split_arguments(arguments: &str) -> Result<Vec<&str>, ParseColorError> {
+    if arguments.contains(',') {
+        let arguments: Vec<&str> = arguments.split(',').map(str::trim).collect();
+        let invalid = |a: &&str| a.is_empty() || a.contains(|c: char| c == '/' || c.is_whitespace());
+        if arguments.iter().any(invalid) {
+            return Err(ParseColorError::InvalidSeparator);
+        }
+        return Ok(arguments);
+    }
+
+    let (components, alpha) = match arguments.split_once('/') {
+        Some((components, alpha)) => (components, Some(alpha)),
+        None => (arguments, None),
+    };
+    let mut arguments: Vec<&str> = components.split_whitespace().collect();
+    match alpha {
+        Some(alpha) => {
+            let alpha: Vec<&str> = alpha.split_whitespace().collect();
+            if arguments.len() != 3 || alpha.len() != 1 || alpha[0].contains('/') {
+                return Err(ParseColorError::InvalidSeparator);
+            }
+            arguments.push(alpha[0]);
+        }
+        None if arguments.len() == 4 => return Err(ParseColorError::InvalidSeparator),
+        None => {}
+    }
+    Ok(arguments)
+}
+
+fn parse_number(value: &str) -> Result<f32, ParseColorError> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| ParseColorError::InvalidNumber(value.to_string()))
+}
+
+/// `0.5` or `50%` to `[0, 1]`
+fn parse_fraction(value: &str) -> Result<f32, ParseColorError> {
+    let fraction = match value.strip_suffix('%') {
+        Some(percent) => parse_number(percent)? / 100.0,
+        None => parse_number(value)?,
+    };
+    Ok(fraction.clamp(0.0, 1.0))
+}
+
+/// Hue in degrees, supports `deg`, `rad`, `grad` and `turn` units
+fn parse_hue(value: &str) -> Result<f32, ParseColorError> {
+    let degrees = if let Some(v) = value.strip_suffix("deg") {
+        parse_number(v)?
+    } else if let Some(v) = value.strip_suffix("grad") {
+        parse_number(v)? * 0.9
+    } else if let Some(v) = value.strip_suffix("rad") {
+        parse_number(v)?.to_degrees()
+    } else if let Some(v) = value.strip_suffix("turn") {
+        parse_number(v)? * 360.0
+    } else {
+        parse_number(value)?
+    };
+    Ok(degrees.rem_euclid(360.0))
+}
+
+/// CSS named color (lowercase), see <https://www.w3.org/TR/css-color-4/#named-colors>
+pub fn named_color(name: &str) -> Option<RGB<u8>> {
+    NAMED_COLORS
+        .binary_search_by(|(n, _)| n.cmp(&name))
+        .ok()
+        .map(|index| RGB::from_pixel(NAMED_COLORS[index].1))
+}
+
+/// Sorted by name, colors are `0xRRGGBB`
+pub const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xF0F8FF),
+    ("antiquewhite", 0xFAEBD7),
+    ("aqua", 0x00FFFF),
+    ("aquamarine", 0x7FFFD4),
+    ("azure", 0xF0FFFF),
+    ("beige", 0xF5F5DC),
+    ("bisque", 0xFFE4C4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xFFEBCD),
+    ("blue", 0x0000FF),
+    ("blueviolet", 0x8A2BE2),
+    ("brown", 0xA52A2A),
+    ("burlywood", 0xDEB887),
+    ("cadetblue", 0x5F9EA0),
+    ("chartreuse", 0x7FFF00),
+    ("chocolate", 0xD2691E),
+    ("coral", 0xFF7F50),
+    ("cornflowerblue", 0x6495ED),
+    ("cornsilk", 0xFFF8DC),
+    ("crimson", 0xDC143C),
+    ("cyan", 0x00FFFF),
+    ("darkblue", 0x00008B),
+    ("darkcyan", 0x008B8B),
+    ("darkgoldenrod", 0xB8860B),
+    ("darkgray", 0xA9A9A9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xA9A9A9),
+    ("darkkhaki", 0xBDB76B),
+    ("darkmagenta", 0x8B008B),
+    ("darkolivegreen", 0x556B2F),
+    ("darkorange", 0xFF8C00),
+    ("darkorchid", 0x9932CC),
+    ("darkred", 0x8B0000),
+    ("darksalmon", 0xE9967A),
+    ("darkseagreen", 0x8FBC8F),
+    ("darkslateblue", 0x483D8B),
+    ("darkslategray", 0x2F4F4F),
+    ("darkslategrey", 0x2F4F4F),
+    ("darkturquoise", 0x00CED1),
+    ("darkviolet", 0x9400D3),
+    ("deeppink", 0xFF1493),
+    ("deepskyblue", 0x00BFFF),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1E90FF),
+    ("firebrick", 0xB22222),
+    ("floralwhite", 0xFFFAF0),
+    ("forestgreen", 0x228B22),
+    ("fuchsia", 0xFF00FF),
+    ("gainsboro", 0xDCDCDC),
+    ("ghostwhite", 0xF8F8FF),
+    ("gold", 0xFFD700),
+    ("goldenrod", 0xDAA520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xADFF2F),
+    ("grey", 0x808080),
+    ("honeydew", 0xF0FFF0),
+    ("hotpink", 0xFF69B4),
+    ("indianred", 0xCD5C5C),
+    ("indigo", 0x4B0082),
+    ("ivory", 0xFFFFF0),
+    ("khaki", 0xF0E68C),
+    ("lavender", 0xE6E6FA),
+    ("lavenderblush", 0xFFF0F5),
+    ("lawngreen", 0x7CFC00),
+    ("lemonchiffon", 0xFFFACD),
+    ("lightblue", 0xADD8E6),
+    ("lightcoral", 0xF08080),
+    ("lightcyan", 0xE0FFFF),
+    ("lightgoldenrodyellow", 0xFAFAD2),
+    ("lightgray", 0xD3D3D3),
+    ("lightgreen", 0x90EE90),
+    ("lightgrey", 0xD3D3D3),
+    ("lightpink", 0xFFB6C1),
+    ("lightsalmon", 0xFFA07A),
+    ("lightseagreen", 0x20B2AA),
+    ("lightskyblue", 0x87CEFA),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xB0C4DE),
+    ("lightyellow", 0xFFFFE0),
+    ("lime", 0x00FF00),
+    ("limegreen", 0x32CD32),
+    ("linen", 0xFAF0E6),
+    ("magenta", 0xFF00FF),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66CDAA),
+    ("mediumblue", 0x0000CD),
+    ("mediumorchid", 0xBA55D3),
+    ("mediumpurple", 0x9370DB),
+    ("mediumseagreen", 0x3CB371),
+    ("mediumslateblue", 0x7B68EE),
+    ("mediumspringgreen", 0x00FA9A),
+    ("mediumturquoise", 0x48D1CC),
+    ("mediumvioletred", 0xC71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xF5FFFA),
+    ("mistyrose", 0xFFE4E1),
+    ("moccasin", 0xFFE4B5),
+    ("navajowhite", 0xFFDEAD),
+    ("navy", 0x000080),
+    ("oldlace", 0xFDF5E6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6B8E23),
+    ("orange", 0xFFA500),
+    ("orangered", 0xFF4500),
+    ("orchid", 0xDA70D6),
+    ("palegoldenrod", 0xEEE8AA),
+    ("palegreen", 0x98FB98),
+    ("paleturquoise", 0xAFEEEE),
+    ("palevioletred", 0xDB7093),
+    ("papayawhip", 0xFFEFD5),
+    ("peachpuff", 0xFFDAB9),
+    ("peru", 0xCD853F),
+    ("pink", 0xFFC0CB),
+    ("plum", 0xDDA0DD),
+    ("powderblue", 0xB0E0E6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xFF0000),
+    ("rosybrown", 0xBC8F8F),
+    ("royalblue", 0x4169E1),
+    ("saddlebrown", 0x8B4513),
+    ("salmon", 0xFA8072),
+    ("sandybrown", 0xF4A460),
+    ("seagreen", 0x2E8B57),
+    ("seashell", 0xFFF5EE),
+    ("sienna", 0xA0522D),
+    ("silver", 0xC0C0C0),
+    ("skyblue", 0x87CEEB),
+    ("slateblue", 0x6A5ACD),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xFFFAFA),
+    ("springgreen", 0x00FF7F),
+    ("steelblue", 0x4682B4),
+    ("tan", 0xD2B48C),
+    ("teal", 0x008080),
+    ("thistle", 0xD8BFD8),
+    ("tomato", 0xFF6347),
+    ("turquoise", 0x40E0D0),
+    ("violet", 0xEE82EE),
+    ("wheat", 0xF5DEB3),
+    ("white", 0xFFFFFF),
+    ("whitesmoke", 0xF5F5F5),
+    ("yellow", 0xFFFF00),
+    ("yellowgreen", 0x9ACD32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(s: &str) -> Result<RGBA<u8>, ParseColorError> {
+        s.parse()
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(rgba("#f80"), Ok(RGBA::new(255, 136, 0, 255)));
+        assert_eq!(rgba("#f808"), Ok(RGBA::new(255, 136, 0, 136)));
+        assert_eq!(rgba("#FF8000"), Ok(RGBA::new(255, 128, 0, 255)));
+        assert_eq!(rgba("#ff800080"), Ok(RGBA::new(255, 128, 0, 128)));
+        assert_eq!(rgba("#ff80"), Ok(RGBA::new(255, 255, 136, 0)));
+        assert_eq!(rgba("#ff800"), Err(ParseColorError::InvalidHexLength(5)));
+        assert_eq!(rgba("#"), Err(ParseColorError::InvalidHexLength(0)));
+        assert_eq!(rgba("#ff80g0"), Err(ParseColorError::InvalidHexDigit('g')));
+    }
+
+    #[test]
+    fn named_colors() {
+        assert!(NAMED_COLORS.windows(2).all(|pair| pair[0].0 < pair[1].0), "NAMED_COLORS must be sorted");
+        for (name, pixel) in NAMED_COLORS {
+            assert_eq!(named_color(name), Some(RGB::from_pixel(pixel)), "{}", name);
+        }
+        assert_eq!("CornflowerBlue".parse::<RGB<u8>>(), Ok(RGB::new(100, 149, 237)));
+        assert_eq!(rgba("transparent"), Ok(RGBA::new(0, 0, 0, 0)));
+        assert_eq!(named_color("blurple"), None);
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(rgba("rgb(255, 128, 0)"), Ok(RGBA::new(255, 128, 0, 255)));
+        assert_eq!(rgba("rgba(100%, 0%, 50%, 0.5)"), Ok(RGBA::new(255, 0, 128, 128)));
+        assert_eq!(rgba("rgb(255 128 0 / 50%)"), Ok(RGBA::new(255, 128, 0, 128)));
+        assert_eq!(rgba("rgb(300 -5 0)"), Ok(RGBA::new(255, 0, 0, 255)));
+        assert_eq!(rgba("hsl(120, 100%, 50%)"), Ok(RGBA::new(0, 255, 0, 255)));
+        assert_eq!(rgba("hsla(240 100% 50% / 0.5)"), Ok(RGBA::new(0, 0, 255, 128)));
+        assert_eq!(rgba("hsl(0.5turn 100% 50%)"), Ok(RGBA::new(0, 255, 255, 255)));
+        assert_eq!(rgba("hsl(-120deg, 100%, 50%)"), Ok(RGBA::new(0, 0, 255, 255)));
+    }
+
+    #[test]
+    fn separators() {
+        assert_eq!(rgba("rgb(1,2,3)"), Ok(RGBA::new(1, 2, 3, 255)));
+        assert_eq!(rgba("rgb( 1 , 2 , 3 )"), Ok(RGBA::new(1, 2, 3, 255)));
+        for s in ["rgb(1,,2,3)", "rgb(1, 2 3)", "rgb(1, 2, 3,)", "rgb(1, 2, 3 / 1)", "rgb(1 2 / 3)", "rgb(1 2 3 0.5)"] {
+            assert_eq!(rgba(s), Err(ParseColorError::InvalidSeparator), "{}", s);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let wrong_count = |found| ParseColorError::WrongArgumentCount { function: "rgb".to_string(), found };
+        assert_eq!(rgba("  "), Err(ParseColorError::Empty));
+        assert_eq!(rgba("blurple"), Err(ParseColorError::UnknownName("blurple".to_string())));
+        assert_eq!(rgba("lab(50 0 0)"), Err(ParseColorError::UnknownFunction("lab".to_string())));
+        assert_eq!(rgba("rgb(1, 2, 3"), Err(ParseColorError::MissingParenthesis));
+        assert_eq!(rgba("rgb(1, 2)"), Err(wrong_count(2)));
+        assert_eq!(rgba("rgb()"), Err(wrong_count(0)));
+        assert_eq!(rgba("rgb(1, x, 3)"), Err(ParseColorError::InvalidNumber("x".to_string())));
+        assert_eq!("#ff000080".parse::<RGB<u8>>(), Err(ParseColorError::UnexpectedAlpha));
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let color = RGBA::new(18, 52, 86, 120);
+        assert_eq!(color.to_string(), "#12345678");
+        assert_eq!(rgba(&color.to_string()), Ok(color));
+        assert_eq!(RGB::new(255, 128, 0).to_string(), "#ff8000");
+    }
+}
@@ -0,0 +1,167 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::spaces::Hsl;
+use super::{contrast_ratio, RGB};
+
+/// `1 / φ`, hue step which never repeats and keeps consecutive colors far apart
+const GOLDEN_RATIO_CONJUGATE: f32 = 0.618_034;
+
+/// Candidates tried before giving up on [`ColorGenerator::min_contrast()`]
+const MAX_ATTEMPTS: usize = 64;
+
+/// Random colors in HSL with constraints. Unlike [`super::random_color()`] it is reproducible
+/// with a seed and avoids muddy or unreadable colors.
+///
+/// Example:
+/// ```
+/// let mut generator = ColorGenerator::from_seed(42)
+///     .hue_range(180.0, 300.0)
+///     .saturation_range(0.5, 0.9)
+///     .lightness_range(0.4, 0.7)
+///     .min_contrast(RGB::new(0, 0, 0), 4.5)
+///     .golden_ratio(true);
+/// let colors: Vec<RGB<u8>> = generator.by_ref().take(8).collect();
+/// ```
+#[derive(Clone, Debug)]
+pub struct ColorGenerator<R: Rng = StdRng> {
+    rng: R,
+    hue_start: f32,
+    /// Degrees from `hue_start`, in `[0, 360]`
+    hue_span: f32,
+    saturation: (f32, f32),
+    lightness: (f32, f32),
+    /// Background and the minimal WCAG contrast ratio against it
+    contrast: Option<(RGB<u8>, f32)>,
+    golden_ratio: bool,
+    /// Position in the hue range for golden ratio stepping, `None` until the first color
+    hue_position: Option<f32>,
+}
+
+impl ColorGenerator<StdRng> {
+    /// Seeded from the OS, results differ on every run
+    pub fn new() -> Self {
+        Self::with_rng(StdRng::from_entropy())
+    }
+
+    /// Same seed gives the same sequence of colors
+    pub fn from_seed(seed: u64) -> Self {
+        Self::with_rng(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for ColorGenerator<StdRng> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: Rng> ColorGenerator<R> {
+    /// Any hue, saturation and lightness
+    pub fn with_rng(rng: R) -> Self {
+        Self {
+            rng,
+            hue_start: 0.0,
+            hue_span: 360.0,
+            saturation: (0.0, 1.0),
+            lightness: (0.0, 1.0),
+            contrast: None,
+            golden_ratio: false,
+            hue_position: None,
+        }
+    }
+
+    /// Hues from `from` to `to` degrees going up, the range may wrap: `(300, 60)` is magenta to yellow through red
+    pub fn hue_range(mut self, from: f32, to: f32) -> Self {
+        let span = to - from;
+        self.hue_start = from.rem_euclid(360.0);
+        self.hue_span = if span.abs() >= 360.0 { 360.0 } else { span.rem_euclid(360.0) };
+        self
+    }
+
+    /// HSL saturation bounds in `[0, 1]`
+    pub fn saturation_range(mut self, min: f32, max: f32) -> Self {
+        self.saturation = ordered_unit_range(min, max);
+        self
+    }
+
+    /// HSL lightness bounds in `[0, 1]`
+    pub fn lightness_range(mut self, min: f32, max: f32) -> Self {
+        self.lightness = ordered_unit_range(min, max);
+        self
+    }
+
+    /// Reject colors with WCAG contrast ratio (1 to 21) against `background` below `ratio`.
+    /// If no color within the bounds is found, the one with the highest contrast is returned
+    pub fn min_contrast(mut self, background: RGB<u8>, ratio: f32) -> Self {
+        self.contrast = Some((background, ratio));
+        self
+    }
+
+    /// Step hue by the golden ratio instead of picking it at random, consecutive colors are visually distinct
+    pub fn golden_ratio(mut self, enabled: bool) -> Self {
+        self.golden_ratio = enabled;
+        self
+    }
+
+    pub fn rng(&mut self) -> &mut R {
+        &mut self.rng
+    }
+
+    pub fn next_color(&mut self) -> RGB<u8> {
+        let hue = self.next_hue();
+        let Some((background, ratio)) = self.contrast else {
+            return self.candidate(hue);
+        };
+
+        let mut best = (self.candidate(hue), 0.0);
+        for _ in 0..MAX_ATTEMPTS {
+            let color = self.candidate(hue);
+            let contrast = contrast_ratio(color, background);
+            if contrast >= ratio {
+                return color;
+            }
+            if contrast > best.1 {
+                best = (color, contrast);
+            }
+        }
+        best.0
+    }
+
+    /// [`Self::next_color()`] as COLORREF, drop-in replacement for [`super::random_color()`]
+    pub fn next_colorref(&mut self) -> u32 {
+        self.next_color().to_colorref()
+    }
+
+    fn next_hue(&mut self) -> f32 {
+        let position = match self.hue_position {
+            Some(position) if self.golden_ratio => (position + GOLDEN_RATIO_CONJUGATE).fract(),
+            _ => self.rng.gen::<f32>(),
+        };
+        self.hue_position = Some(position);
+        (self.hue_start + position * self.hue_span).rem_euclid(360.0)
+    }
+
+    fn candidate(&mut self, hue: f32) -> RGB<u8> {
+        let s = lerp_range(self.saturation, self.rng.gen());
+        let l = lerp_range(self.lightness, self.rng.gen());
+        RGB::<u8>::from_hsl(Hsl { h: hue, s, l })
+    }
+}
+
+impl<R: Rng> Iterator for ColorGenerator<R> {
+    type Item = RGB<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_color())
+    }
+}
+
+fn ordered_unit_range(a: f32, b: f32) -> (f32, f32) {
+    let (a, b) = (a.clamp(0.0, 1.0), b.clamp(0.0, 1.0));
+    (a.min(b), a.max(b))
+}
+
+fn lerp_range(range: (f32, f32), t: f32) -> f32 {
+    range.0 + (range.1 - range.0) * t
+}
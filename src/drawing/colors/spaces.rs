@@ -0,0 +1,444 @@
+use super::RGB;
+
+/// Hue in degrees `[0, 360)`, saturation and value in `[0, 1]`
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Hsv {
+    pub h: f32,
+    pub s: f32,
+    pub v: f32,
+}
+
+/// Hue in degrees `[0, 360)`, saturation and lightness in `[0, 1]`
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Hsl {
+    pub h: f32,
+    pub s: f32,
+    pub l: f32,
+}
+
+/// Perceptual color space, see <https://bottosson.github.io/posts/oklab/>.
+/// Lightness in `[0, 1]`, `a` and `b` are about `[-0.4, 0.4]`
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Oklab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+/// Polar form of [`Oklab`]: lightness, chroma and hue in degrees `[0, 360)`
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Oklch {
+    pub l: f32,
+    pub c: f32,
+    pub h: f32,
+}
+
+/// Space in which colors are interpolated and mixed
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum ColorSpace {
+    /// Gamma-encoded sRGB components, as they are stored
+    #[default]
+    Srgb,
+    LinearRgb,
+    Hsv,
+    Hsl,
+    Oklab,
+    Oklch,
+}
+
+impl Hsv {
+    #[inline(always)]
+    pub const fn new(h: f32, s: f32, v: f32) -> Self {
+        Self { h, s, v }
+    }
+}
+
+impl Hsl {
+    #[inline(always)]
+    pub const fn new(h: f32, s: f32, l: f32) -> Self {
+        Self { h, s, l }
+    }
+}
+
+impl Oklab {
+    #[inline(always)]
+    pub const fn new(l: f32, a: f32, b: f32) -> Self {
+        Self { l, a, b }
+    }
+
+    pub fn to_oklch(self) -> Oklch {
+        Oklch {
+            l: self.l,
+            c: self.a.hypot(self.b),
+            h: normalize_hue(self.b.atan2(self.a).to_degrees()),
+        }
+    }
+}
+
+impl Oklch {
+    #[inline(always)]
+    pub const fn new(l: f32, c: f32, h: f32) -> Self {
+        Self { l, c, h }
+    }
+
+    pub fn to_oklab(self) -> Oklab {
+        let (sin, cos) = self.h.to_radians().sin_cos();
+        Oklab { l: self.l, a: self.c * cos, b: self.c * sin }
+    }
+}
+
+/// Wrap hue in degrees to `[0, 360)`
+#[inline(always)]
+pub fn normalize_hue(h: f32) -> f32 {
+    let h = h.rem_euclid(360.0);
+    if h >= 360.0 { 0.0 } else { h }
+}
+
+/// sRGB transfer function: gamma-encoded component in `[0, 1]` to linear light
+#[inline(always)]
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Inverse of [`srgb_to_linear()`]
+#[inline(always)]
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+impl RGB<u8> {
+    /// Components in `[0, 1]`
+    pub fn to_f32(self) -> RGB<f32> {
+        RGB::new(self.r as f32 / 255.0, self.g as f32 / 255.0, self.b as f32 / 255.0)
+    }
+
+    pub fn to_linear(self) -> RGB<f32> {
+        self.to_f32().to_linear()
+    }
+
+    pub fn from_linear(linear: RGB<f32>) -> Self {
+        linear.to_srgb().to_u8()
+    }
+
+    pub fn to_hsv(self) -> Hsv {
+        self.to_f32().to_hsv()
+    }
+
+    pub fn from_hsv(hsv: Hsv) -> Self {
+        RGB::<f32>::from_hsv(hsv).to_u8()
+    }
+
+    pub fn to_hsl(self) -> Hsl {
+        self.to_f32().to_hsl()
+    }
+
+    pub fn from_hsl(hsl: Hsl) -> Self {
+        RGB::<f32>::from_hsl(hsl).to_u8()
+    }
+
+    pub fn to_oklab(self) -> Oklab {
+        self.to_f32().to_oklab()
+    }
+
+    pub fn from_oklab(lab: Oklab) -> Self {
+        RGB::<f32>::from_oklab(lab).to_u8()
+    }
+
+    pub fn to_oklch(self) -> Oklch {
+        self.to_oklab().to_oklch()
+    }
+
+    pub fn from_oklch(lch: Oklch) -> Self {
+        Self::from_oklab(lch.to_oklab())
+    }
+}
+
+/// Components in `[0, 1]`. Gamma-encoded sRGB unless stated otherwise
+impl RGB<f32> {
+    /// Clamp components to `[0, 1]` and round to bytes
+    pub fn to_u8(self) -> RGB<u8> {
+        let convert = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        RGB::new(convert(self.r), convert(self.g), convert(self.b))
+    }
+
+    /// sRGB to linear light
+    pub fn to_linear(self) -> RGB<f32> {
+        RGB::new(srgb_to_linear(self.r), srgb_to_linear(self.g), srgb_to_linear(self.b))
+    }
+
+    /// Linear light to sRGB
+    pub fn to_srgb(self) -> RGB<f32> {
+        RGB::new(linear_to_srgb(self.r), linear_to_srgb(self.g), linear_to_srgb(self.b))
+    }
+
+    pub fn to_hsv(self) -> Hsv {
+        let (max, min, h) = hue_max_min(self);
+        let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+        Hsv { h, s, v: max }
+    }
+
+    pub fn from_hsv(hsv: Hsv) -> Self {
+        let c = hsv.v * hsv.s;
+        from_hue_chroma(hsv.h, c, hsv.v - c)
+    }
+
+    pub fn to_hsl(self) -> Hsl {
+        let (max, min, h) = hue_max_min(self);
+        let l = (max + min) / 2.0;
+        let s = if max == min { 0.0 } else { (max - min) / (1.0 - (2.0 * l - 1.0).abs()) };
+        Hsl { h, s, l }
+    }
+
+    pub fn from_hsl(hsl: Hsl) -> Self {
+        let c = (1.0 - (2.0 * hsl.l - 1.0).abs()) * hsl.s;
+        from_hue_chroma(hsl.h, c, hsl.l - c / 2.0)
+    }
+
+    pub fn to_oklab(self) -> Oklab {
+        let RGB { r, g, b } = self.to_linear();
+
+        let l = (0.41222146 * r + 0.53633255 * g + 0.051445995 * b).cbrt();
+        let m = (0.2119035 * r + 0.6806995 * g + 0.10739696 * b).cbrt();
+        let s = (0.08830246 * r + 0.28171885 * g + 0.6299787 * b).cbrt();
+
+        Oklab {
+            l: 0.21045426 * l + 0.7936178 * m - 0.004072047 * s,
+            a: 1.9779985 * l - 2.4285922 * m + 0.4505937 * s,
+            b: 0.025904037 * l + 0.78277177 * m - 0.80867577 * s,
+        }
+    }
+
+    /// Result can be out of `[0, 1]` for colors outside of sRGB gamut
+    pub fn from_oklab(lab: Oklab) -> Self {
+        let l = lab.l + 0.39633778 * lab.a + 0.21580376 * lab.b;
+        let m = lab.l - 0.105561346 * lab.a - 0.06385417 * lab.b;
+        let s = lab.l - 0.08948418 * lab.a - 1.2914855 * lab.b;
+        let (l, m, s) = (l * l * l, m * m * m, s * s * s);
+
+        RGB::new(
+            4.0767417 * l - 3.3077116 * m + 0.23096994 * s,
+            -1.268438 * l + 2.6097574 * m - 0.34131938 * s,
+            -0.0041960863 * l - 0.7034186 * m + 1.7076147 * s,
+        )
+        .to_srgb()
+    }
+
+    pub fn to_oklch(self) -> Oklch {
+        self.to_oklab().to_oklch()
+    }
+
+    pub fn from_oklch(lch: Oklch) -> Self {
+        Self::from_oklab(lch.to_oklab())
+    }
+}
+
+/// (max, min, hue) of the color
+fn hue_max_min(color: RGB<f32>) -> (f32, f32, f32) {
+    let RGB { r, g, b } = color;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let h = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (max, min, normalize_hue(h))
+}
+
+/// Color from hue, chroma and the value added to every component
+fn from_hue_chroma(h: f32, c: f32, m: f32) -> RGB<f32> {
+    let h = normalize_hue(h) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    RGB::new(r + m, g + m, b + m)
+}
+
+/// Saturation or chroma below this is considered gray
+const ACHROMATIC_THRESHOLD: f32 = 1e-4;
+
+impl ColorSpace {
+    /// Color components in this space. For spaces with hue it is at [`ColorSpace::hue_index()`]
+    pub fn to_components(self, color: RGB<u8>) -> [f32; 3] {
+        match self {
+            ColorSpace::Srgb => [color.r as f32, color.g as f32, color.b as f32],
+            ColorSpace::LinearRgb => {
+                let c = color.to_linear();
+                [c.r, c.g, c.b]
+            }
+            ColorSpace::Hsv => {
+                let c = color.to_hsv();
+                [c.h, c.s, c.v]
+            }
+            ColorSpace::Hsl => {
+                let c = color.to_hsl();
+                [c.h, c.s, c.l]
+            }
+            ColorSpace::Oklab => {
+                let c = color.to_oklab();
+                [c.l, c.a, c.b]
+            }
+            ColorSpace::Oklch => {
+                let c = color.to_oklch();
+                [c.l, c.c, c.h]
+            }
+        }
+    }
+
+    pub fn from_components(self, c: [f32; 3]) -> RGB<u8> {
+        match self {
+            ColorSpace::Srgb => RGB::<f32>::new(c[0] / 255.0, c[1] / 255.0, c[2] / 255.0).to_u8(),
+            ColorSpace::LinearRgb => RGB::<u8>::from_linear(RGB::new(c[0], c[1], c[2])),
+            ColorSpace::Hsv => RGB::<u8>::from_hsv(Hsv::new(c[0], c[1], c[2])),
+            ColorSpace::Hsl => RGB::<u8>::from_hsl(Hsl::new(c[0], c[1], c[2])),
+            ColorSpace::Oklab => RGB::<u8>::from_oklab(Oklab::new(c[0], c[1], c[2])),
+            ColorSpace::Oklch => RGB::<u8>::from_oklch(Oklch::new(c[0], c[1], c[2])),
+        }
+    }
+
+    /// Index of the hue component in degrees, it is interpolated along the shorter arc.
+    /// Saturation or chroma is always at index 1
+    pub fn hue_index(self) -> Option<usize> {
+        match self {
+            ColorSpace::Hsv | ColorSpace::Hsl => Some(0),
+            ColorSpace::Oklch => Some(2),
+            _ => None,
+        }
+    }
+
+    /// Interpolate two colors, `weight` in `[0, 1]`
+    pub fn lerp(self, from: RGB<u8>, to: RGB<u8>, weight: f32) -> RGB<u8> {
+        let mut a = self.to_components(from);
+        let mut b = self.to_components(to);
+        if let Some(hue) = self.hue_index() {
+            // Hue of gray is meaningless, use the hue of the other color
+            if a[1] < ACHROMATIC_THRESHOLD {
+                a[hue] = b[hue];
+            } else if b[1] < ACHROMATIC_THRESHOLD {
+                b[hue] = a[hue];
+            }
+            b[hue] = a[hue] + shortest_hue_delta(a[hue], b[hue]);
+        }
+
+        let mut c = [0.0; 3];
+        for i in 0..3 {
+            c[i] = (1.0 - weight) * a[i] + weight * b[i];
+        }
+        self.from_components(c)
+    }
+
+    /// Weighted average of colors. Hue is averaged on the circle, grays do not affect it
+    pub fn mix(self, colors: &[RGB<u8>], proportions: &[f32]) -> RGB<u8> {
+        let total_prop = proportions.iter().sum::<f32>();
+        let hue = self.hue_index();
+
+        let mut sum = [0.0f32; 3];
+        let (mut hue_x, mut hue_y) = (0.0f32, 0.0f32);
+        for (color, p) in colors.iter().zip(proportions) {
+            let c = self.to_components(*color);
+            for i in 0..3 {
+                if Some(i) == hue {
+                    if c[1] < ACHROMATIC_THRESHOLD {
+                        continue;
+                    }
+                    let (sin, cos) = c[i].to_radians().sin_cos();
+                    hue_x += cos * p;
+                    hue_y += sin * p;
+                } else {
+                    sum[i] += c[i] * p;
+                }
+            }
+        }
+
+        let mut c = sum.map(|v| v / total_prop);
+        if let Some(hue) = hue {
+            c[hue] = normalize_hue(hue_y.atan2(hue_x).to_degrees());
+        }
+        self.from_components(c)
+    }
+}
+
+/// Signed difference from hue `a` to hue `b` in `[-180, 180]` degrees
+#[inline(always)]
+pub fn shortest_hue_delta(a: f32, b: f32) -> f32 {
+    let delta = (b - a).rem_euclid(360.0);
+    if delta > 180.0 { delta - 360.0 } else { delta }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every 15th value of each channel, includes black, white and the primaries
+    fn samples() -> impl Iterator<Item = RGB<u8>> {
+        let values = || (0..=255u8).step_by(15);
+        values().flat_map(move |r| values().flat_map(move |g| values().map(move |b| RGB::new(r, g, b))))
+    }
+
+    fn assert_close(a: RGB<u8>, b: RGB<u8>, space: ColorSpace) {
+        let close = a.r.abs_diff(b.r) <= 1 && a.g.abs_diff(b.g) <= 1 && a.b.abs_diff(b.b) <= 1;
+        assert!(close, "{:?} came back as {:?} through {:?}", a, b, space);
+    }
+
+    #[test]
+    fn round_trips_within_one() {
+        let spaces = [
+            ColorSpace::Srgb,
+            ColorSpace::LinearRgb,
+            ColorSpace::Hsv,
+            ColorSpace::Hsl,
+            ColorSpace::Oklab,
+            ColorSpace::Oklch,
+        ];
+        for space in spaces {
+            for color in samples() {
+                assert_close(color, space.from_components(space.to_components(color)), space);
+            }
+        }
+    }
+
+    #[test]
+    fn direct_conversions_round_trip() {
+        for color in samples() {
+            assert_close(color, RGB::<u8>::from_hsv(color.to_hsv()), ColorSpace::Hsv);
+            assert_close(color, RGB::<u8>::from_hsl(color.to_hsl()), ColorSpace::Hsl);
+            assert_close(color, RGB::<u8>::from_linear(color.to_linear()), ColorSpace::LinearRgb);
+            assert_close(color, RGB::<u8>::from_oklab(color.to_oklab()), ColorSpace::Oklab);
+            assert_close(color, RGB::<u8>::from_oklch(color.to_oklch()), ColorSpace::Oklch);
+        }
+    }
+
+    #[test]
+    fn reference_values() {
+        let red = RGB::new(255, 0, 0);
+        assert_eq!(red.to_hsv(), Hsv::new(0.0, 1.0, 1.0));
+        assert_eq!(red.to_hsl(), Hsl::new(0.0, 1.0, 0.5));
+        assert!((srgb_to_linear(0.5) - 0.2140).abs() < 1e-3);
+
+        let white = RGB::new(255, 255, 255).to_oklab();
+        assert!((white.l - 1.0).abs() < 1e-3 && white.a.abs() < 1e-3 && white.b.abs() < 1e-3);
+        // Reference from the Oklab post: sRGB red is L 0.628, a 0.225, b 0.126
+        let red = red.to_oklab();
+        assert!((red.l - 0.628).abs() < 1e-3 && (red.a - 0.225).abs() < 1e-3 && (red.b - 0.126).abs() < 1e-3);
+    }
+}
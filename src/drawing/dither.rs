@@ -0,0 +1,251 @@
+use std::sync::OnceLock;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::colors::palette::Palette;
+use super::colors::RGB;
+use super::framebuffer::Framebuffer;
+
+/// Side of the generated blue noise texture
+const BLUE_NOISE_SIZE: usize = 64;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Dither {
+    /// Plain nearest color, shows banding
+    None,
+    /// Ordered dithering with `size` x `size` Bayer matrix, `size` is a power of two up to 16.
+    /// Fast and stable between frames, but has a visible cross-hatch pattern
+    Bayer(usize),
+    /// Error diffusion with serpentine scanning. Best quality for stills, but flickers in animations
+    #[default]
+    FloydSteinberg,
+    /// Ordered dithering with a blue noise texture, pattern is not visible and stable between frames
+    BlueNoise,
+}
+
+/// Reduce colors of the whole `framebuffer` to `palette`, e.g. as a post-process for e-ink or low color displays.
+/// Alpha byte is kept. Panics if the palette is empty
+pub fn dither(framebuffer: &mut Framebuffer, palette: &Palette, method: Dither) {
+    match method {
+        Dither::None => ordered(framebuffer, palette, |_, _| 0.0),
+        Dither::Bayer(size) => {
+            let matrix = bayer_matrix(size);
+            let size = (matrix.len() as f64).sqrt() as usize;
+            ordered(framebuffer, palette, |x, y| matrix[(y % size) * size + x % size])
+        }
+        Dither::FloydSteinberg => floyd_steinberg(framebuffer, palette),
+        Dither::BlueNoise => {
+            let noise = blue_noise();
+            ordered(framebuffer, palette, |x, y| {
+                noise[(y % BLUE_NOISE_SIZE) * BLUE_NOISE_SIZE + x % BLUE_NOISE_SIZE]
+            })
+        }
+    }
+}
+
+/// Thresholds in `(-0.5, 0.5)` of the Bayer matrix, row by row. `size` is rounded up to a power of two in `[2, 16]`
+pub fn bayer_matrix(size: usize) -> Vec<f32> {
+    let size = size.clamp(2, 16).next_power_of_two();
+    let mut matrix = vec![0usize];
+    let mut n = 1;
+    // M(2n) = [4M, 4M + 2; 4M + 3, 4M + 1]
+    while n < size {
+        let mut next = vec![0; 4 * n * n];
+        for y in 0..n {
+            for x in 0..n {
+                let value = 4 * matrix[y * n + x];
+                next[y * 2 * n + x] = value;
+                next[y * 2 * n + x + n] = value + 2;
+                next[(y + n) * 2 * n + x] = value + 3;
+                next[(y + n) * 2 * n + x + n] = value + 1;
+            }
+        }
+        matrix = next;
+        n *= 2;
+    }
+
+    let count = (size * size) as f32;
+    matrix.into_iter().map(|rank| (rank as f32 + 0.5) / count - 0.5).collect()
+}
+
+/// Add `threshold(x, y)` scaled by the palette spacing, then pick the nearest color
+fn ordered<F: Fn(usize, usize) -> f32>(framebuffer: &mut Framebuffer, palette: &Palette, threshold: F) {
+    let spread = palette_spread(palette);
+    let width = framebuffer.width();
+    for (i, pixel) in framebuffer.pixels_mut().iter_mut().enumerate() {
+        let offset = threshold(i % width, i / width) * spread;
+        let color = RGB::from_pixel(*pixel);
+        let shift = |c: u8| (c as f32 + offset).round().clamp(0.0, 255.0) as u8;
+        let shifted = RGB::new(shift(color.r), shift(color.g), shift(color.b));
+        *pixel = (*pixel & 0xFF00_0000) | palette.nearest(shifted).to_pixel();
+    }
+}
+
+/// Typical distance between palette colors per channel, as if the colors were a uniform RGB grid
+fn palette_spread(palette: &Palette) -> f32 {
+    let levels = (palette.len() as f32).cbrt().max(2.0);
+    255.0 / (levels - 1.0)
+}
+
+fn floyd_steinberg(framebuffer: &mut Framebuffer, palette: &Palette) {
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+    // Accumulated error of the current and the next row, 3 channels per pixel with 1 pixel padding on both sides
+    let mut current = vec![[0.0f32; 3]; width + 2];
+    let mut next = vec![[0.0f32; 3]; width + 2];
+    let pixels = framebuffer.pixels_mut();
+
+    for y in 0..height {
+        let forward = y % 2 == 0;
+        for step in 0..width {
+            let x = if forward { step } else { width - 1 - step };
+            let pixel = &mut pixels[y * width + x];
+            let color = RGB::from_pixel(*pixel);
+            let error = current[x + 1];
+            let wanted = [color.r as f32 + error[0], color.g as f32 + error[1], color.b as f32 + error[2]];
+            let clamp = |c: f32| c.round().clamp(0.0, 255.0) as u8;
+            let chosen = palette.nearest(RGB::new(clamp(wanted[0]), clamp(wanted[1]), clamp(wanted[2])));
+            *pixel = (*pixel & 0xFF00_0000) | chosen.to_pixel();
+
+            let chosen = [chosen.r as f32, chosen.g as f32, chosen.b as f32];
+            // Neighbours in scan direction: ahead, behind below, below, ahead below
+            let (ahead, behind) = if forward { (x + 2, x) } else { (x, x + 2) };
+            for c in 0..3 {
+                let e = wanted[c] - chosen[c];
+                current[ahead][c] += e * 7.0 / 16.0;
+                next[behind][c] += e * 3.0 / 16.0;
+                next[x + 1][c] += e * 5.0 / 16.0;
+                next[ahead][c] += e * 1.0 / 16.0;
+            }
+        }
+        std::mem::swap(&mut current, &mut next);
+        next.fill([0.0; 3]);
+    }
+}
+
+/// Thresholds in `(-0.5, 0.5)` of the blue noise texture, generated once with void-and-cluster
+fn blue_noise() -> &'static [f32] {
+    static NOISE: OnceLock<Vec<f32>> = OnceLock::new();
+    NOISE.get_or_init(|| {
+        let count = BLUE_NOISE_SIZE * BLUE_NOISE_SIZE;
+        let ranks = void_and_cluster(BLUE_NOISE_SIZE, 1.5, 0);
+        ranks.into_iter().map(|rank| (rank as f32 + 0.5) / count as f32 - 0.5).collect()
+    })
+}
+
+/// Ulichney's void-and-cluster: rank of every pixel of `size` x `size` tileable blue noise.
+/// `sigma` is the radius of the Gaussian energy filter
+fn void_and_cluster(size: usize, sigma: f32, seed: u64) -> Vec<usize> {
+    let count = size * size;
+    // Gaussian weight for every toroidal offset
+    let kernel: Vec<f32> = (0..count)
+        .map(|i| {
+            let wrap = |d: usize| d.min(size - d) as f32;
+            let (dx, dy) = (wrap(i % size), wrap(i / size));
+            (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+
+    let mut pattern = vec![false; count];
+    let mut energy = vec![0.0f32; count];
+    let toggle = |pattern: &mut Vec<bool>, energy: &mut Vec<f32>, index: usize| {
+        pattern[index] = !pattern[index];
+        let sign = if pattern[index] { 1.0 } else { -1.0 };
+        let (ix, iy) = (index % size, index / size);
+        for (p, e) in energy.iter_mut().enumerate() {
+            let dx = (p % size + size - ix) % size;
+            let dy = (p / size + size - iy) % size;
+            *e += sign * kernel[dy * size + dx];
+        }
+    };
+    // Tightest cluster is the set pixel with the highest energy, largest void is the empty one with the lowest
+    let tightest_cluster = |pattern: &[bool], energy: &[f32]| {
+        (0..count).filter(|&i| pattern[i]).max_by(|&a, &b| energy[a].total_cmp(&energy[b])).unwrap_or(0)
+    };
+    let largest_void = |pattern: &[bool], energy: &[f32]| {
+        (0..count).filter(|&i| !pattern[i]).min_by(|&a, &b| energy[a].total_cmp(&energy[b])).unwrap_or(0)
+    };
+
+    // Initial random pattern with 10% of pixels set, relaxed until stable
+    let mut rng = StdRng::seed_from_u64(seed);
+    let initial_count = count / 10;
+    while pattern.iter().filter(|&&set| set).count() < initial_count {
+        let index = rng.gen_range(0..count);
+        if !pattern[index] {
+            toggle(&mut pattern, &mut energy, index);
+        }
+    }
+    for _ in 0..count {
+        let cluster = tightest_cluster(&pattern, &energy);
+        toggle(&mut pattern, &mut energy, cluster);
+        let void = largest_void(&pattern, &energy);
+        toggle(&mut pattern, &mut energy, void);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; count];
+    // Phase 1: remove clusters from a copy, they get ranks below the initial count
+    let (mut phase_pattern, mut phase_energy) = (pattern.clone(), energy.clone());
+    for rank in (0..initial_count).rev() {
+        let cluster = tightest_cluster(&phase_pattern, &phase_energy);
+        toggle(&mut phase_pattern, &mut phase_energy, cluster);
+        ranks[cluster] = rank;
+    }
+    // Phase 2: fill voids until the pattern is full
+    for rank in initial_count..count {
+        let void = largest_void(&pattern, &energy);
+        toggle(&mut pattern, &mut energy, void);
+        ranks[void] = rank;
+    }
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn black_and_white() -> Palette {
+        Palette::new(vec![RGB::new(0, 0, 0), RGB::new(255, 255, 255)])
+    }
+
+    /// White pixels of `size` x `size` 50% grey dithered to black and white
+    fn white_count(method: Dither, size: usize) -> usize {
+        let mut framebuffer = Framebuffer::new(size, size);
+        framebuffer.fill(0xFF80_8080);
+        dither(&mut framebuffer, &black_and_white(), method);
+
+        assert!(framebuffer.pixels().iter().all(|&pixel| pixel == 0xFF00_0000 || pixel == 0xFFFF_FFFF));
+        framebuffer.pixels().iter().filter(|&&pixel| pixel == 0xFFFF_FFFF).count()
+    }
+
+    #[test]
+    fn grey_is_half_white() {
+        assert_eq!(white_count(Dither::Bayer(2), 16), 128);
+        assert_eq!(white_count(Dither::Bayer(8), 16), 128);
+        assert_eq!(white_count(Dither::FloydSteinberg, 16), 128);
+        assert_eq!(white_count(Dither::None, 16), 256);
+        // One whole texture has every threshold once, 128 is slightly above the middle of 0 and 255
+        assert_eq!(white_count(Dither::BlueNoise, BLUE_NOISE_SIZE), 2056);
+        let half = 16 * 16 / 2;
+        assert!(white_count(Dither::BlueNoise, 16).abs_diff(half) < 8);
+    }
+
+    #[test]
+    fn bayer_matrices() {
+        assert_eq!(bayer_matrix(2), [-0.375, 0.125, 0.375, -0.125]);
+        assert_eq!(bayer_matrix(3).len(), 16);
+        let mut ranks = bayer_matrix(16);
+        ranks.sort_by(f32::total_cmp);
+        assert!(ranks.iter().enumerate().all(|(i, &value)| value == (i as f32 + 0.5) / 256.0 - 0.5));
+    }
+
+    #[test]
+    fn empty_framebuffer() {
+        for method in [Dither::None, Dither::Bayer(4), Dither::FloydSteinberg, Dither::BlueNoise] {
+            dither(&mut Framebuffer::new(0, 0), &black_and_white(), method);
+            dither(&mut Framebuffer::new(0, 3), &black_and_white(), method);
+        }
+    }
+}
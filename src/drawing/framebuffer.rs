@@ -0,0 +1,376 @@
+use super::canvas::Surface;
+use super::clip::{clip_line, intersect_spans, polygon_row_spans, ClipRegion, Rect};
+use super::text::{layout_text, rasterize_text, Font, TextStyle};
+use super::image::{sample, ScaleFilter};
+use super::colors::RGBA;
+use super::colors::compositing::{composite_premultiplied, premultiply, unpremultiply, CompositeOp};
+
+/// Software [`Surface`]. Pixels are stored row by row (top-down) in `0x00RRGGBB` format,
+/// same as 32-bit DIB, so it can be presented with [`super::primitives::draw_framebuffer()`].
+///
+/// Framebuffer with alpha ([`Framebuffer::has_alpha()`]) keeps straight alpha in the top byte (`0xAARRGGBB`),
+/// it is used by [`Framebuffer::composite()`]. Otherwise all pixels are opaque.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+    clip: Option<ClipRegion>,
+    alpha: bool,
+}
+
+/// Convert COLORREF (`0x00BBGGRR`) to framebuffer pixel (`0x00RRGGBB`) and vice versa
+#[inline(always)]
+pub const fn swap_red_blue(color: u32) -> u32 {
+    (color & 0xFF00) | ((color & 0xFF) << 16) | ((color >> 16) & 0xFF)
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, pixels: vec![0; width * height], clip: None, alpha: false }
+    }
+
+    /// `pixels` must be <b>width * height</b> size, e.g. result of [`super::beauty_math::calc_mandelbrot()`]
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<u32>) -> Self {
+        assert_eq!(pixels.len(), width * height, "pixels must be width * height size");
+        Self { width, height, pixels, clip: None, alpha: false }
+    }
+
+    /// Treat the top byte of pixels as alpha
+    pub fn with_alpha(mut self, alpha: bool) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        self.alpha
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        &mut self.pixels
+    }
+
+    pub fn into_pixels(self) -> Vec<u32> {
+        self.pixels
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Option<u32> {
+        if x < self.width && y < self.height {
+            Some(self.pixels[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    /// `pixel` in `0x00RRGGBB` format. Out of bounds writes are ignored
+    pub fn set_pixel(&mut self, x: usize, y: usize, pixel: u32) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = pixel;
+        }
+    }
+
+    /// `pixel` in `0x00RRGGBB` format. Ignores the clip region
+    pub fn fill(&mut self, pixel: u32) {
+        self.pixels.fill(pixel);
+    }
+
+    pub fn clip(&self) -> Option<&ClipRegion> {
+        self.clip.as_ref()
+    }
+
+    /// Composite `layer` placed at (`x`, `y`) with Porter–Duff `op`. `opacity` in `[0, 1]` multiplies the layer alpha.
+    /// Respects the clip region
+    pub fn composite(&mut self, layer: &Framebuffer, x: i64, y: i64, op: CompositeOp, opacity: f32) {
+        for layer_y in 0..layer.height {
+            for layer_x in 0..layer.width {
+                let Some(index) = self.clipped_index(x + layer_x as i64, y + layer_y as i64) else {
+                    continue;
+                };
+                let source = layer.color_at(layer.pixels[layer_y * layer.width + layer_x]);
+                self.composite_pixel(index, source, op, opacity);
+            }
+        }
+    }
+
+    /// Composite solid `color` over the whole surface, e.g. translucent black for fading trails.
+    /// Respects the clip region
+    pub fn fill_composite(&mut self, color: RGBA<u8>, op: CompositeOp) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if let Some(index) = self.clipped_index(x as i64, y as i64) {
+                    self.composite_pixel(index, color, op, 1.0);
+                }
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn color_at(&self, pixel: u32) -> RGBA<u8> {
+        let color = RGBA::from_argb(pixel);
+        if self.alpha { color } else { RGBA { a: 255, ..color } }
+    }
+
+    #[inline(always)]
+    fn composite_pixel(&mut self, index: usize, source: RGBA<u8>, op: CompositeOp, opacity: f32) {
+        let mut source = premultiply(source.to_f32());
+        source = RGBA::new(source.r * opacity, source.g * opacity, source.b * opacity, source.a * opacity);
+        let destination = premultiply(self.color_at(self.pixels[index]).to_f32());
+        let result = composite_premultiplied(source, destination, op);
+
+        self.pixels[index] = if self.alpha {
+            unpremultiply(result).to_u8().to_argb()
+        } else {
+            // Opaque surface shows the result over black
+            RGBA { a: 0.0, ..result }.to_u8().to_argb()
+        };
+    }
+
+    /// Draw `image` scaled to `rect` (in pixels). Image with alpha is composited with [`CompositeOp::SourceOver`].
+    /// Respects the clip region
+    pub fn draw_image(&mut self, image: &Framebuffer, rect: Rect, filter: ScaleFilter) {
+        if image.width == 0 || image.height == 0 || rect.is_empty() {
+            return;
+        }
+        let bounds = self.clip_bounds().intersect(&rect);
+        if bounds.is_empty() {
+            return;
+        }
+
+        let scale_x = image.width as f64 / rect.width();
+        let scale_y = image.height as f64 / rect.height();
+        // Pixel is covered if its centre is inside the rect
+        let from_x = (bounds.left - 0.5).ceil().max(0.0) as usize;
+        let to_x = ((bounds.right - 0.5).ceil().max(0.0) as usize).min(self.width);
+        let from_y = (bounds.top - 0.5).ceil().max(0.0) as usize;
+        let to_y = ((bounds.bottom - 0.5).ceil().max(0.0) as usize).min(self.height);
+        for y in from_y..to_y {
+            let v = (y as f64 + 0.5 - rect.top) * scale_y;
+            for x in from_x..to_x {
+                if let Some(index) = self.clipped_index(x as i64, y as i64) {
+                    let u = (x as f64 + 0.5 - rect.left) * scale_x;
+                    let pixel = sample(image, u, v, filter);
+                    if !image.alpha {
+                        self.pixels[index] = if self.alpha { pixel | 0xFF000000 } else { pixel };
+                    } else if self.alpha && self.pixels[index] >> 24 == 0 {
+                        // Over a transparent pixel the source is the result, copied without rounding
+                        self.pixels[index] = pixel;
+                    } else {
+                        self.composite_pixel(index, RGBA::from_argb(pixel), CompositeOp::SourceOver, 1.0);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Surface bounds intersected with the clip region bounds
+    fn clip_bounds(&self) -> Rect {
+        let bounds = Rect::from_size(self.width, self.height);
+        match &self.clip {
+            Some(clip) => bounds.intersect(&clip.bounds),
+            None => bounds,
+        }
+    }
+
+    #[inline(always)]
+    fn plot(&mut self, x: i64, y: i64, pixel: u32) {
+        if let Some(index) = self.clipped_index(x, y) {
+            self.pixels[index] = pixel;
+        }
+    }
+
+    /// Framebuffer pixel of COLORREF `color`, opaque on surfaces with alpha
+    #[inline(always)]
+    fn opaque_pixel(&self, color: u32) -> u32 {
+        let pixel = swap_red_blue(color);
+        if self.alpha { pixel | 0xFF000000 } else { pixel }
+    }
+
+    /// Mix `pixel` over existing one with `alpha` in `[0, 1]`. Surface with alpha gets the coverage in its alpha too
+    #[inline(always)]
+    fn blend(&mut self, x: i64, y: i64, pixel: u32, alpha: f32) {
+        let Some(index) = self.clipped_index(x, y) else {
+            return;
+        };
+        if self.alpha {
+            let source = RGBA::from_argb(pixel | 0xFF000000);
+            self.composite_pixel(index, source, CompositeOp::SourceOver, alpha.clamp(0.0, 1.0));
+            return;
+        }
+        let alpha = (alpha.clamp(0.0, 1.0) * 256.0) as u32;
+        let dst = self.pixels[index];
+        let mix = |shift: u32| {
+            let (s, d) = ((pixel >> shift) & 0xFF, (dst >> shift) & 0xFF);
+            ((s * alpha + d * (256 - alpha)) >> 8) << shift
+        };
+        self.pixels[index] = mix(16) | mix(8) | mix(0);
+    }
+
+    /// Index of the pixel if it is inside the surface and the clip region
+    #[inline(always)]
+    fn clipped_index(&self, x: i64, y: i64) -> Option<usize> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        if let Some(clip) = &self.clip {
+            if !clip.contains((x as f64 + 0.5, y as f64 + 0.5)) {
+                return None;
+            }
+        }
+        Some(y as usize * self.width + x as usize)
+    }
+
+    /// Fill rows `from..to`, `row_spans` returns spans of the shape for the row centre.
+    /// Spans are clipped by the clip region
+    fn fill_rows(&mut self, from: f64, to: f64, pixel: u32, mut row_spans: impl FnMut(f64, &mut Vec<(f64, f64)>)) {
+        let bounds = self.clip_bounds();
+        if bounds.is_empty() {
+            return;
+        }
+        let from = (from.max(bounds.top) - 0.5).ceil().max(0.0) as usize;
+        let to = ((to.min(bounds.bottom) - 0.5).ceil().max(0.0) as usize).min(self.height);
+
+        let clip = self.clip.take();
+        let mut spans = Vec::new();
+        let mut path_spans = Vec::new();
+        let mut clipped = Vec::new();
+        for y in from..to {
+            let center_y = y as f64 + 0.5;
+            row_spans(center_y, &mut spans);
+            for path in clip.iter().flat_map(|clip| clip.paths.iter()) {
+                polygon_row_spans(path, center_y, &mut path_spans);
+                intersect_spans(&spans, &path_spans, &mut clipped);
+                std::mem::swap(&mut spans, &mut clipped);
+            }
+            for span in &spans {
+                self.fill_span(y, span.0.max(bounds.left), span.1.min(bounds.right), pixel);
+            }
+        }
+        self.clip = clip;
+    }
+
+    fn fill_span(&mut self, y: usize, x_from: f64, x_to: f64, pixel: u32) {
+        // Pixel is covered if its centre is inside the span
+        let from = (x_from - 0.5).ceil().max(0.0) as usize;
+        let to = ((x_to - 0.5).ceil().max(0.0) as usize).min(self.width);
+        if from < to {
+            let row = y * self.width;
+            self.pixels[row + from..row + to].fill(pixel);
+        }
+    }
+}
+
+impl Surface for Framebuffer {
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn set_clip(&mut self, clip: Option<&ClipRegion>) {
+        self.clip = clip.cloned();
+    }
+
+    /// Bresenham line, clipped with Liang–Barsky to the clip bounds first
+    fn draw_line(&mut self, from: (f64, f64), to: (f64, f64), color: u32) {
+        let Some((from, to)) = clip_line(&self.clip_bounds(), from, to) else {
+            return;
+        };
+        let pixel = self.opaque_pixel(color);
+
+        let (mut x, mut y) = (from.0.floor() as i64, from.1.floor() as i64);
+        let (x_end, y_end) = (to.0.floor() as i64, to.1.floor() as i64);
+        let dx = (x_end - x).abs();
+        let dy = -(y_end - y).abs();
+        let sx = if x < x_end { 1 } else { -1 };
+        let sy = if y < y_end { 1 } else { -1 };
+        let mut error = dx + dy;
+        loop {
+            self.plot(x, y, pixel);
+            if x == x_end && y == y_end {
+                break;
+            }
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Scanline fill, pixel is covered if its centre is inside the polygon
+    fn fill_polygon(&mut self, points: &[(f64, f64)], color: u32) {
+        if points.len() < 3 {
+            return;
+        }
+        let bounds = Rect::bounding(points);
+        self.fill_rows(bounds.top, bounds.bottom, self.opaque_pixel(color), |y, spans| {
+            polygon_row_spans(points, y, spans)
+        });
+    }
+
+    fn fill_circle(&mut self, center: (f64, f64), radius: f64, color: u32) {
+        if radius <= 0.0 {
+            return;
+        }
+        self.fill_rows(center.1 - radius, center.1 + radius, self.opaque_pixel(color), |y, spans| {
+            let dy = y - center.1;
+            let dx = (radius * radius - dy * dy).max(0.0).sqrt();
+            spans.clear();
+            spans.push((center.0 - dx, center.0 + dx));
+        });
+    }
+
+    /// Anti-aliased text, rasterized in pure Rust
+    fn draw_text(&mut self, font: &Font, text: &str, position: (f64, f64), style: &TextStyle) {
+        let layout = layout_text(font, text, style);
+        let pixel = (style.color.r as u32) << 16 | (style.color.g as u32) << 8 | style.color.b as u32;
+        rasterize_text(font, &layout, style, position, |x, y, alpha| self.blend(x, y, pixel, alpha));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitives_are_opaque_on_alpha_layers() {
+        let mut layer = Framebuffer::new(8, 8).with_alpha(true);
+        layer.draw_line((0.5, 0.5), (7.5, 0.5), 0x0000FF);
+        layer.fill_polygon(&[(0.0, 2.0), (8.0, 2.0), (8.0, 4.0), (0.0, 4.0)], 0x00FF00);
+        layer.fill_circle((4.0, 6.0), 1.0, 0xFF0000);
+
+        let mut target = Framebuffer::new(8, 8);
+        target.fill(0x808080);
+        target.composite(&layer, 0, 0, CompositeOp::SourceOver, 1.0);
+        assert_eq!(target.get_pixel(3, 0), Some(0xFF0000));
+        assert_eq!(target.get_pixel(3, 3), Some(0x00FF00));
+        assert_eq!(target.get_pixel(4, 6), Some(0x0000FF));
+        assert_eq!(target.get_pixel(3, 1), Some(0x808080));
+    }
+
+    #[test]
+    fn blend_accumulates_coverage_on_alpha_layers() {
+        let mut layer = Framebuffer::new(1, 1).with_alpha(true);
+        layer.blend(0, 0, 0xFF0000, 0.5);
+        let pixel = layer.pixels()[0];
+        assert_eq!(pixel & 0xFFFFFF, 0xFF0000);
+        assert!((pixel >> 24).abs_diff(128) <= 1, "{:08X}", pixel);
+
+        layer.blend(0, 0, 0xFF0000, 1.0);
+        assert_eq!(layer.pixels()[0], 0xFFFF0000);
+    }
+}
@@ -0,0 +1,207 @@
+use std::path::Path;
+
+use super::clip::Rect;
+use super::framebuffer::Framebuffer;
+
+/// How the picture is placed on the surface. Same modes as Windows offers for static wallpapers
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ScaleMode {
+    /// Keep aspect ratio and cover the whole surface, the picture is cropped
+    #[default]
+    Fill,
+    /// Keep aspect ratio and show the whole picture, uncovered borders are not changed
+    Fit,
+    /// Cover the whole surface, aspect ratio is not kept
+    Stretch,
+    /// Original size at the centre of the surface
+    Center,
+    /// Original size repeated from the top left corner
+    Tile,
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ScaleFilter {
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+/// Load PNG, JPEG or BMP file. Result has alpha ([`Framebuffer::has_alpha()`]), pixels are `0xAARRGGBB`
+pub fn load_image<P: AsRef<Path>>(path: P) -> Result<Framebuffer, ::image::ImageError> {
+    let image = ::image::open(path)?;
+    Ok(from_dynamic_image(image))
+}
+
+/// Like [`load_image()`], format is detected from the data
+pub fn load_image_from_memory(data: &[u8]) -> Result<Framebuffer, ::image::ImageError> {
+    let image = ::image::load_from_memory(data)?;
+    Ok(from_dynamic_image(image))
+}
+
+fn from_dynamic_image(image: ::image::DynamicImage) -> Framebuffer {
+    let image = image.into_rgba8();
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let pixels = image
+        .pixels()
+        .map(|p| (p[3] as u32) << 24 | (p[0] as u32) << 16 | (p[1] as u32) << 8 | p[2] as u32)
+        .collect();
+    Framebuffer::from_pixels(width, height, pixels).with_alpha(true)
+}
+
+/// Rectangles where the `image_size` picture is drawn on `surface_size` surface with `mode`
+pub fn placement(mode: ScaleMode, image_size: (usize, usize), surface_size: (usize, usize)) -> Vec<Rect> {
+    let (image_w, image_h) = (image_size.0 as f64, image_size.1 as f64);
+    let (surface_w, surface_h) = (surface_size.0 as f64, surface_size.1 as f64);
+    if image_w == 0.0 || image_h == 0.0 {
+        return Vec::new();
+    }
+
+    let centered = |width: f64, height: f64| {
+        let left = ((surface_w - width) / 2.0).round();
+        let top = ((surface_h - height) / 2.0).round();
+        Rect::new(left, top, left + width, top + height)
+    };
+
+    match mode {
+        ScaleMode::Fill => {
+            let scale = (surface_w / image_w).max(surface_h / image_h);
+            vec![centered(image_w * scale, image_h * scale)]
+        }
+        ScaleMode::Fit => {
+            let scale = (surface_w / image_w).min(surface_h / image_h);
+            vec![centered(image_w * scale, image_h * scale)]
+        }
+        ScaleMode::Stretch => vec![Rect::new(0.0, 0.0, surface_w, surface_h)],
+        ScaleMode::Center => vec![centered(image_w, image_h)],
+        ScaleMode::Tile => {
+            let mut rects = Vec::new();
+            let mut top = 0.0;
+            while top < surface_h {
+                let mut left = 0.0;
+                while left < surface_w {
+                    rects.push(Rect::new(left, top, left + image_w, top + image_h));
+                    left += image_w;
+                }
+                top += image_h;
+            }
+            rects
+        }
+    }
+}
+
+/// Draw `image` over the whole `target` with `mode`. Respects the target clip region
+pub fn draw_wallpaper(target: &mut Framebuffer, image: &Framebuffer, mode: ScaleMode, filter: ScaleFilter) {
+    for rect in placement(mode, (image.width(), image.height()), (target.width(), target.height())) {
+        target.draw_image(image, rect, filter);
+    }
+}
+
+/// Resize `image` to `width` x `height`, alpha is kept
+pub fn scale_image(image: &Framebuffer, width: usize, height: usize, filter: ScaleFilter) -> Framebuffer {
+    let mut scaled = Framebuffer::new(width, height).with_alpha(image.has_alpha());
+    scaled.draw_image(image, Rect::from_size(width, height), filter);
+    scaled
+}
+
+/// Sample `image` at (`u`, `v`) in image pixel coordinates, pixel centres are at `x + 0.5`.
+/// Coordinates outside the image are clamped to the edge. Result keeps straight alpha if the image has it
+pub fn sample(image: &Framebuffer, u: f64, v: f64, filter: ScaleFilter) -> u32 {
+    let pixels = image.pixels();
+    let (width, height) = (image.width(), image.height());
+    let clamp_x = |x: f64| (x.max(0.0) as usize).min(width - 1);
+    let clamp_y = |y: f64| (y.max(0.0) as usize).min(height - 1);
+
+    match filter {
+        ScaleFilter::Nearest => pixels[clamp_y(v) * width + clamp_x(u)],
+        ScaleFilter::Bilinear => {
+            let (u, v) = (u - 0.5, v - 0.5);
+            let (x0, y0) = (u.floor(), v.floor());
+            let (tx, ty) = (u - x0, v - y0);
+            let (left, right) = (clamp_x(x0), clamp_x(x0 + 1.0));
+            let (top, bottom) = (clamp_y(y0), clamp_y(y0 + 1.0));
+            let corners = [
+                pixels[top * width + left],
+                pixels[top * width + right],
+                pixels[bottom * width + left],
+                pixels[bottom * width + right],
+            ];
+            let weights = [(1.0 - tx) * (1.0 - ty), tx * (1.0 - ty), (1.0 - tx) * ty, tx * ty];
+
+            // Colors are weighted by alpha (premultiplied), so transparent pixels do not darken the edges
+            let alpha = |pixel: u32| if image.has_alpha() { (pixel >> 24) as f64 } else { 255.0 };
+            let coverage: f64 = corners.iter().zip(weights).map(|(&pixel, weight)| alpha(pixel) * weight).sum();
+            if coverage <= 0.0 {
+                return 0;
+            }
+
+            let channel = |shift: u32| {
+                let value: f64 = corners
+                    .iter()
+                    .zip(weights)
+                    .map(|(&pixel, weight)| ((pixel >> shift) & 0xFF) as f64 * alpha(pixel) * weight)
+                    .sum();
+                ((value / coverage).round() as u32).min(255) << shift
+            };
+            let alpha = if image.has_alpha() { (coverage.round() as u32).min(255) << 24 } else { 0 };
+            alpha | channel(16) | channel(8) | channel(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_repeats_pixels() {
+        let image = Framebuffer::from_pixels(2, 2, vec![1, 2, 3, 4]);
+        let scaled = scale_image(&image, 4, 4, ScaleFilter::Nearest);
+        assert_eq!(scaled.pixels(), [1, 1, 2, 2, 1, 1, 2, 2, 3, 3, 4, 4, 3, 3, 4, 4]);
+    }
+
+    #[test]
+    fn bilinear_interpolates_between_centres() {
+        let image = Framebuffer::from_pixels(2, 1, vec![0x000000, 0xFFFFFF]);
+        let scaled = scale_image(&image, 4, 1, ScaleFilter::Bilinear);
+        let blue: Vec<u32> = scaled.pixels().iter().map(|pixel| pixel & 0xFF).collect();
+        assert_eq!(blue, [0, 64, 191, 255]);
+    }
+
+    #[test]
+    fn placement_rects() {
+        let wide = (200, 100);
+        let square = (100, 100);
+        assert_eq!(placement(ScaleMode::Fit, wide, square), [Rect::new(0.0, 25.0, 100.0, 75.0)]);
+        assert_eq!(placement(ScaleMode::Fill, wide, square), [Rect::new(-50.0, 0.0, 150.0, 100.0)]);
+        assert_eq!(placement(ScaleMode::Center, wide, square), [Rect::new(-50.0, 0.0, 150.0, 100.0)]);
+        assert_eq!(placement(ScaleMode::Center, (50, 20), square), [Rect::new(25.0, 40.0, 75.0, 60.0)]);
+        assert_eq!(placement(ScaleMode::Stretch, wide, square), [Rect::new(0.0, 0.0, 100.0, 100.0)]);
+
+        let tiles = placement(ScaleMode::Tile, (30, 40), square);
+        assert_eq!(tiles.len(), 4 * 3);
+        assert_eq!(tiles[5], Rect::new(30.0, 40.0, 60.0, 80.0));
+        assert!(placement(ScaleMode::Fill, (0, 10), square).is_empty());
+    }
+
+    #[test]
+    fn bilinear_does_not_darken_transparent_edges() {
+        // Fully transparent black next to opaque red
+        let image = Framebuffer::from_pixels(2, 1, vec![0x00000000, 0xFFFF0000]).with_alpha(true);
+        let scaled = scale_image(&image, 4, 1, ScaleFilter::Bilinear);
+        assert_eq!(scaled.pixels(), [0x00000000, 0x40FF0000, 0xBFFF0000, 0xFFFF0000]);
+    }
+
+    #[test]
+    fn transparent_image_is_composited_over_the_target() {
+        let image = Framebuffer::from_pixels(3, 1, vec![0x00000000, 0x80FF0000, 0xFFFF0000]).with_alpha(true);
+        let mut target = Framebuffer::new(3, 1);
+        target.fill(0x0000FF);
+        target.draw_image(&image, Rect::from_size(3, 1), ScaleFilter::Nearest);
+
+        let pixels = target.pixels();
+        assert_eq!(pixels[0], 0x0000FF);
+        assert_eq!(pixels[2] & 0xFFFFFF, 0xFF0000);
+        let (red, blue) = ((pixels[1] >> 16) & 0xFF, pixels[1] & 0xFF);
+        assert!(red.abs_diff(128) <= 1 && blue.abs_diff(127) <= 1, "{:08X}", pixels[1]);
+    }
+}
@@ -7,9 +7,17 @@ use winapi::um::wingdi::{
     CreatePen,
     MoveToEx,
     LineTo, CreateCompatibleDC, CreateCompatibleBitmap, BitBlt, SRCCOPY, DeleteDC, Ellipse,
+    Polygon, GetStockObject, SetDIBitsToDevice,
+    CreateRectRgn, CreatePolygonRgn, CombineRgn, SelectClipRgn,
+    CreateFontW, SetTextColor, SetBkMode, SetTextAlign, TextOutW, GetTextMetricsW,
+    AddFontMemResourceEx, RemoveFontMemResourceEx,
 };
 
-use winapi::um::wingdi::PS_SOLID;
+use winapi::um::wingdi::{PS_SOLID, NULL_PEN, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, ALTERNATE, RGN_AND};
+use winapi::um::wingdi::{
+    TEXTMETRICW, FW_NORMAL, DEFAULT_CHARSET, OUT_TT_PRECIS, CLIP_DEFAULT_PRECIS, ANTIALIASED_QUALITY, DEFAULT_PITCH,
+    TRANSPARENT, TA_TOP, TA_LEFT, TA_CENTER, TA_RIGHT, TA_NOUPDATECP,
+};
 
 use winapi::um::winuser::{
     PAINTSTRUCT,
@@ -18,8 +26,15 @@ use winapi::um::winuser::{
 
 use winapi::shared::windef::{
     HDC,
-    COLORREF, HBITMAP, RECT, HBRUSH, HPEN, HGDIOBJ,
+    COLORREF, HBITMAP, RECT, HBRUSH, HPEN, HGDIOBJ, POINT,
 };
+use winapi::um::winnt::HANDLE;
+
+use super::canvas::{Canvas, Surface};
+use super::clip::ClipRegion;
+use super::text::{Font, TextStyle, TextAlign};
+use super::beauty_math::SpiralParams;
+use super::framebuffer::Framebuffer;
 
 pub struct DrawFrameData {
     pub hdc: HDC,
@@ -33,6 +48,186 @@ pub struct SolidPenData {
     old_pen: HGDIOBJ,
 }
 
+/// GDI [`Surface`]. Usually `hdc` is a memory DC from [`open_draw_frame()`]
+pub struct GdiSurface {
+    pub hdc: HDC,
+    width: usize,
+    height: usize,
+}
+
+impl GdiSurface {
+    pub fn new(hdc: HDC, width: usize, height: usize) -> Self {
+        Self { hdc, width, height }
+    }
+}
+
+impl Surface for GdiSurface {
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn set_clip(&mut self, clip: Option<&ClipRegion>) {
+        let Some(clip) = clip else {
+            unsafe { SelectClipRgn(self.hdc, null_mut()) };
+            return;
+        };
+
+        unsafe {
+            let bounds = &clip.bounds;
+            let region = CreateRectRgn(
+                bounds.left.round() as i32,
+                bounds.top.round() as i32,
+                bounds.right.round() as i32,
+                bounds.bottom.round() as i32,
+            );
+            for path in &clip.paths {
+                let points = to_gdi_points(path);
+                let path_region = CreatePolygonRgn(points.as_ptr(), points.len() as i32, ALTERNATE);
+                CombineRgn(region, region, path_region, RGN_AND);
+                DeleteObject(path_region as _);
+            }
+            // Region is copied by GDI
+            SelectClipRgn(self.hdc, region);
+            DeleteObject(region as _);
+        }
+    }
+
+    fn draw_line(&mut self, from: (f64, f64), to: (f64, f64), color: u32) {
+        let pen_data = create_solid_pen(self.hdc, color);
+        draw_line(self.hdc, to_gdi_point(from), to_gdi_point(to));
+        close_draw_lines(pen_data);
+    }
+
+    fn draw_polyline(&mut self, points: &[(f64, f64)], color: u32) {
+        let pen_data = create_solid_pen(self.hdc, color);
+        for segment in points.windows(2) {
+            draw_line(self.hdc, to_gdi_point(segment[0]), to_gdi_point(segment[1]));
+        }
+        close_draw_lines(pen_data);
+    }
+
+    fn fill_polygon(&mut self, points: &[(f64, f64)], color: u32) {
+        let points = to_gdi_points(points);
+        let (brush, old_brush) = change_solid_brush(self.hdc, color);
+        unsafe {
+            let old_pen = SelectObject(self.hdc, GetStockObject(NULL_PEN as i32));
+            Polygon(self.hdc, points.as_ptr(), points.len() as i32);
+            SelectObject(self.hdc, old_pen);
+        }
+        revert_brush(self.hdc, brush, old_brush);
+    }
+
+    fn fill_circle(&mut self, center: (f64, f64), radius: f64, color: u32) {
+        let (x, y) = to_gdi_point(center);
+        let (brush, old_brush) = change_solid_brush(self.hdc, color);
+        unsafe {
+            let old_pen = SelectObject(self.hdc, GetStockObject(NULL_PEN as i32));
+            draw_circle(self.hdc, x, y, radius.round() as i32);
+            SelectObject(self.hdc, old_pen);
+        }
+        revert_brush(self.hdc, brush, old_brush);
+    }
+
+    /// Text is drawn by GDI with the font [`Font::family_name()`]. Kerning is not applied.
+    /// A font without family name is reported and not drawn, GDI would silently use its default font
+    fn draw_text(&mut self, font: &Font, text: &str, position: (f64, f64), style: &TextStyle) {
+        let Some(family_name) = font.family_name() else {
+            eprintln!("Font has no family name, GDI can not select it. Text is not drawn: {:?}", text);
+            return;
+        };
+        let face_name = crate::wide_null(family_name);
+        let color = style.color;
+        let align = match style.align {
+            TextAlign::Left => TA_LEFT,
+            TextAlign::Center => TA_CENTER,
+            TextAlign::Right => TA_RIGHT,
+        };
+        let (x, y) = to_gdi_point(position);
+
+        unsafe {
+            let h_font = CreateFontW(
+                -(style.size.round() as i32),
+                0,
+                0,
+                0,
+                FW_NORMAL,
+                0,
+                0,
+                0,
+                DEFAULT_CHARSET,
+                OUT_TT_PRECIS,
+                CLIP_DEFAULT_PRECIS,
+                ANTIALIASED_QUALITY,
+                DEFAULT_PITCH,
+                face_name.as_ptr(),
+            );
+            let old_font = SelectObject(self.hdc, h_font as _);
+            let old_color = SetTextColor(self.hdc, winapi::um::wingdi::RGB(color.r, color.g, color.b));
+            let old_mode = SetBkMode(self.hdc, TRANSPARENT as i32);
+            let old_align = SetTextAlign(self.hdc, align | TA_TOP | TA_NOUPDATECP);
+
+            let mut metrics = TEXTMETRICW::default();
+            GetTextMetricsW(self.hdc, &mut metrics);
+            let line_height = (metrics.tmHeight + metrics.tmExternalLeading) as f64 * style.line_spacing;
+
+            for (i, line) in text.lines().enumerate() {
+                let line: Vec<u16> = line.encode_utf16().collect();
+                let line_y = y + (i as f64 * line_height).round() as i32;
+                TextOutW(self.hdc, x, line_y, line.as_ptr(), line.len() as i32);
+            }
+
+            SetTextAlign(self.hdc, old_align);
+            SetBkMode(self.hdc, old_mode);
+            SetTextColor(self.hdc, old_color);
+            SelectObject(self.hdc, old_font);
+            DeleteObject(h_font as _);
+        }
+    }
+}
+
+/// Font registered in GDI for this process. Unregistered on drop
+pub struct GdiFontResource {
+    handle: HANDLE,
+}
+
+impl Drop for GdiFontResource {
+    fn drop(&mut self) {
+        unsafe { RemoveFontMemResourceEx(self.handle) };
+    }
+}
+
+/// Make `font` available for GDI text drawing ([`GdiSurface`]) without installing it
+pub fn register_gdi_font(font: &Font) -> Result<GdiFontResource, &'static str> {
+    if font.family_name().is_none() {
+        return Err("Font has no family name, GDI can not select it!");
+    }
+    let data = font.data();
+    let mut fonts_count = 0;
+    let handle = unsafe {
+        AddFontMemResourceEx(data.as_ptr() as *mut _, data.len() as u32, null_mut(), &mut fonts_count)
+    };
+    if handle.is_null() {
+        return Err("GDI failed to register the font!");
+    }
+
+    Ok(GdiFontResource { handle })
+}
+
+#[inline(always)]
+fn to_gdi_point(point: (f64, f64)) -> (i32, i32) {
+    (point.0.round() as i32, point.1.round() as i32)
+}
+
+fn to_gdi_points(points: &[(f64, f64)]) -> Vec<POINT> {
+    points
+        .iter()
+        .map(|p| {
+            let (x, y) = to_gdi_point(*p);
+            POINT { x, y }
+        })
+        .collect()
+}
+
 /// Return (brush, old_brush)
 pub fn change_solid_brush(hdc: HDC, color: u32) -> (HBRUSH, HBRUSH) {
     let brush: HBRUSH = unsafe { CreateSolidBrush(color) };
@@ -106,6 +301,37 @@ pub fn draw_circle(hdc: HDC, x: i32, y: i32, radius: i32) {
     }
 }
 
+/// Copy software framebuffer to `hdc` at (`x`, `y`)
+///
+/// # Safety
+/// `hdc` must be a valid device context for the whole call, e.g. the one returned by `BeginPaint`
+pub unsafe fn draw_framebuffer(hdc: HDC, x: i32, y: i32, framebuffer: &Framebuffer) {
+    let mut bmi = BITMAPINFO::default();
+    bmi.bmiHeader.biSize = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
+    bmi.bmiHeader.biWidth = framebuffer.width() as i32;
+    bmi.bmiHeader.biHeight = -(framebuffer.height() as i32); // top-down rows
+    bmi.bmiHeader.biPlanes = 1;
+    bmi.bmiHeader.biBitCount = 32;
+    bmi.bmiHeader.biCompression = BI_RGB;
+
+    unsafe {
+        SetDIBitsToDevice(
+            hdc,
+            x,
+            y,
+            framebuffer.width() as u32,
+            framebuffer.height() as u32,
+            0,
+            0,
+            0,
+            framebuffer.height() as u32,
+            framebuffer.pixels().as_ptr().cast(),
+            &bmi,
+            DIB_RGB_COLORS,
+        );
+    }
+}
+
 pub fn draw_fullscreen_rect(hdc: HDC, ps: &PAINTSTRUCT, color: COLORREF) {
     let rect = &ps.rcPaint;
     unsafe {
@@ -115,36 +341,13 @@ pub fn draw_fullscreen_rect(hdc: HDC, ps: &PAINTSTRUCT, color: COLORREF) {
     }
 }
 
+/// Classic white spiral in the middle of a 1920x1080 `hdc`, same as [`draw_spiral_with()`] with default params
 pub fn draw_spiral(hdc: HDC) {
-    let mut angle = 0.0f32;
-    let radius_mul = 10.0f32;
-    let start_x : f32 = 1920.0 / 2.0;
-    let start_y : f32 = 1080.0 / 2.0;
-
-    let white_color = 0xFFFFFF;
-
-    let brush: HBRUSH = unsafe { CreateSolidBrush(white_color) };
-    let old_brush = unsafe { SelectObject(hdc, brush as _) };
-
-    for i in 0..1000 {
-        // Compute radius based on angle
-        let radius = angle.powf(0.8);
-
-        // Convert polar coordinates to Cartesian coordinates
-        let x = start_x + radius * angle.cos() * radius_mul;
-        let y = start_y + radius * angle.sin() * radius_mul;
-        draw_circle(hdc, x as i32, y as i32, 3);
-
-        // Increment the angle for the next iteration
-        let c = ((i / 500) as f32).powf(0.4) + 1f32;
-        let p = 0.05 / c;
-        angle += p;
-    }
-
-    unsafe {
-        SelectObject(hdc, old_brush);
-        winapi::um::wingdi::DeleteObject(brush as _);
-    }
+    draw_spiral_with(hdc, 1920, 1080, &SpiralParams::default());
+}
 
-    // ToDo: Add custom parameters!
-}
\ No newline at end of file
+/// Draw [`SpiralParams`] spiral over whole `width` x `height` area of `hdc`
+pub fn draw_spiral_with(hdc: HDC, width: usize, height: usize, params: &SpiralParams) {
+    let mut canvas = Canvas::new(GdiSurface::new(hdc, width, height));
+    params.draw(&mut canvas, 0.0);
+}
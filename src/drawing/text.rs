@@ -0,0 +1,309 @@
+use std::fmt;
+use std::path::Path;
+
+use ab_glyph::{point, Font as _, FontVec, GlyphId, PxScale, ScaleFont};
+
+use super::colors::RGB;
+
+/// TrueType / OpenType font, rasterized in pure Rust (see [`super::framebuffer::Framebuffer`]).
+///
+/// GDI draws text by the font [`Font::family_name()`], so the font must be installed or registered
+/// with [`super::primitives::register_gdi_font()`].
+pub struct Font {
+    font: FontVec,
+    family_name: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(std::io::Error),
+    /// File is not a TrueType / OpenType font
+    InvalidFont,
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::Io(error) => write!(f, "Could not read the font file: {}", error),
+            FontError::InvalidFont => write!(f, "Invalid TrueType / OpenType font data"),
+        }
+    }
+}
+
+impl std::error::Error for FontError {}
+
+impl From<std::io::Error> for FontError {
+    fn from(error: std::io::Error) -> Self {
+        FontError::Io(error)
+    }
+}
+
+impl Font {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Font, FontError> {
+        Font::from_bytes(std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Font, FontError> {
+        let family_name = read_family_name(&data);
+        let font = FontVec::try_from_vec(data).map_err(|_| FontError::InvalidFont)?;
+        Ok(Font { font, family_name })
+    }
+
+    /// Raw font file data
+    pub fn data(&self) -> &[u8] {
+        self.font.font_data()
+    }
+
+    /// Family name from the `name` table, e.g. "Segoe UI"
+    pub fn family_name(&self) -> Option<&str> {
+        self.family_name.as_deref()
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TextStyle {
+    /// Font size (height of the em square) in pixels
+    pub size: f64,
+    pub color: RGB<u8>,
+    /// Horizontal alignment of every line relative to the text position
+    pub align: TextAlign,
+    /// Multiplier for the font line height
+    pub line_spacing: f64,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle {
+            size: 16.0,
+            color: RGB::new(255, 255, 255),
+            align: TextAlign::Left,
+            line_spacing: 1.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PositionedGlyph {
+    pub id: u16,
+    /// Position of the glyph origin at the baseline, relative to the text position
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Result of [`layout_text()`]. Coordinates are relative to the text position (top of the first line)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextLayout {
+    pub glyphs: Vec<PositionedGlyph>,
+    /// Width of every line
+    pub line_widths: Vec<f64>,
+    pub line_height: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Place glyphs of multi-line `text` with kerning and alignment
+pub fn layout_text(font: &Font, text: &str, style: &TextStyle) -> TextLayout {
+    let scaled = font.font.as_scaled(PxScale::from(style.size as f32));
+    let line_height = (scaled.height() + scaled.line_gap()) as f64 * style.line_spacing;
+    let ascent = scaled.ascent() as f64;
+
+    let mut layout = TextLayout { line_height, ..Default::default() };
+    for (line_index, line) in text.lines().enumerate() {
+        let baseline = ascent + line_index as f64 * line_height;
+        let first_glyph = layout.glyphs.len();
+
+        let mut x = 0.0f64;
+        let mut previous: Option<GlyphId> = None;
+        for c in line.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                x += scaled.kern(previous, id) as f64;
+            }
+            layout.glyphs.push(PositionedGlyph { id: id.0, x, y: baseline });
+            x += scaled.h_advance(id) as f64;
+            previous = Some(id);
+        }
+
+        let offset = match style.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => -x / 2.0,
+            TextAlign::Right => -x,
+        };
+        for glyph in &mut layout.glyphs[first_glyph..] {
+            glyph.x += offset;
+        }
+        layout.line_widths.push(x);
+        layout.width = layout.width.max(x);
+    }
+    layout.height = layout.line_widths.len() as f64 * line_height;
+    layout
+}
+
+/// Rasterize laid out glyphs. `coverage(x, y, alpha)` is called for every covered pixel in device coordinates
+pub fn rasterize_text(
+    font: &Font,
+    layout: &TextLayout,
+    style: &TextStyle,
+    position: (f64, f64),
+    mut coverage: impl FnMut(i64, i64, f32),
+) {
+    let scale = PxScale::from(style.size as f32);
+    for glyph in &layout.glyphs {
+        let glyph = GlyphId(glyph.id).with_scale_and_position(
+            scale,
+            point((position.0 + glyph.x) as f32, (position.1 + glyph.y) as f32),
+        );
+        if let Some(outlined) = font.font.outline_glyph(glyph) {
+            let bounds = outlined.px_bounds();
+            outlined.draw(|x, y, alpha| {
+                coverage(bounds.min.x as i64 + x as i64, bounds.min.y as i64 + y as i64, alpha);
+            });
+        }
+    }
+}
+
+/// Read family name (name id 1) from the `name` table of TrueType / OpenType font or the first font of collection
+fn read_family_name(data: &[u8]) -> Option<String> {
+    let u16_at = |offset: usize| data.get(offset..offset + 2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+    let u32_at = |offset: usize| data.get(offset..offset + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+
+    let font_offset = if data.get(0..4)? == b"ttcf" { u32_at(12)? as usize } else { 0 };
+    let num_tables = u16_at(font_offset + 4)? as usize;
+    let name_table = (0..num_tables)
+        .map(|i| font_offset + 12 + i * 16)
+        .find(|record| data.get(*record..*record + 4) == Some(&b"name"[..]))
+        .and_then(|record| u32_at(record + 8))? as usize;
+
+    let count = u16_at(name_table + 2)? as usize;
+    let strings = name_table + u16_at(name_table + 4)? as usize;
+    let mut mac_name = None;
+    for i in 0..count {
+        let record = name_table + 6 + i * 12;
+        let (platform, name_id) = (u16_at(record)?, u16_at(record + 6)?);
+        if name_id != 1 {
+            continue;
+        }
+        let length = u16_at(record + 8)? as usize;
+        let offset = strings + u16_at(record + 10)? as usize;
+        let bytes = data.get(offset..offset + length)?;
+        match platform {
+            // Unicode and Windows platforms use UTF-16BE
+            0 | 3 => {
+                let wide: Vec<u16> = bytes.chunks_exact(2).map(|b| u16::from_be_bytes([b[0], b[1]])).collect();
+                return String::from_utf16(&wide).ok();
+            }
+            1 => mac_name = Some(bytes.iter().map(|b| *b as char).collect()),
+            _ => {}
+        }
+    }
+    mac_name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// TrueType font with the tables `ttf-parser` needs, no outlines. 1000 units per em, ascent 800, descent -200,
+    /// line gap 100. Glyphs: 1 `A` and 2 `V` advance 600 and kern -100 as `AV`, 3 space advances 250.
+    /// `names` are `(platform, name id, string)` records, `base` is the offset of the font in the file
+    fn test_font(names: &[(u16, u16, &[u8])], base: usize) -> Vec<u8> {
+        let be16 = |values: &[i32]| values.iter().flat_map(|&v| (v as u16).to_be_bytes()).collect::<Vec<u8>>();
+        let mut head = be16(&[1, 0, 1, 0, 0, 0, 0x5F0F, 0x3CF5, 0, 1000]);
+        head.extend([0; 16]);
+        head.extend(be16(&[0, -200, 600, 800, 0, 3, 2, 0, 0]));
+        let hhea = be16(&[1, 0, 800, -200, 100, 600, 0, 0, 600, 1, 0, 0, 0, 0, 0, 0, 0, 4]);
+        let maxp = be16(&[0, 0x5000, 4]);
+        let hmtx = be16(&[500, 0, 600, 0, 600, 0, 250, 0]);
+        // Format 4 segments for space, A, V and the final 0xFFFF
+        let mut cmap = be16(&[0, 1, 3, 1, 0, 12, 4, 48, 0, 8, 8, 2, 0]);
+        cmap.extend(be16(&[0x20, 0x41, 0x56, 0xFFFF, 0, 0x20, 0x41, 0x56, 0xFFFF]));
+        cmap.extend(be16(&[3 - 0x20, 1 - 0x41, 2 - 0x56, 1, 0, 0, 0, 0]));
+        let kern = be16(&[0, 1, 0, 20, 1, 1, 6, 0, 0, 1, 2, -100]);
+        let mut name = be16(&[0, names.len() as i32, 6 + 12 * names.len() as i32]);
+        let mut strings = Vec::new();
+        for &(platform, name_id, string) in names {
+            name.extend(be16(&[platform as i32, 0, 0, name_id as i32, string.len() as i32, strings.len() as i32]));
+            strings.extend_from_slice(string);
+        }
+        name.extend(strings);
+
+        let tables = [(b"cmap", cmap), (b"head", head), (b"hhea", hhea), (b"hmtx", hmtx), (b"kern", kern)];
+        let tables: Vec<_> = tables.into_iter().chain([(b"maxp", maxp), (b"name", name)]).collect();
+        let mut font = be16(&[1, 0, tables.len() as i32, 0, 0, 0]);
+        let mut data = Vec::new();
+        let start = base + 12 + 16 * tables.len();
+        for (tag, table) in &tables {
+            font.extend_from_slice(*tag);
+            font.extend([0; 4]);
+            font.extend(((start + data.len()) as u32).to_be_bytes());
+            font.extend((table.len() as u32).to_be_bytes());
+            data.extend(table);
+            data.resize(data.len().next_multiple_of(4), 0);
+        }
+        font.extend(data);
+        font
+    }
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    #[test]
+    fn family_name() {
+        let windows = utf16("Test Sans");
+        let font = Font::from_bytes(test_font(&[(3, 2, &utf16("Bold")), (3, 1, &windows)], 0)).unwrap();
+        assert_eq!(font.family_name(), Some("Test Sans"));
+
+        // Mac Roman only when there is no Unicode name
+        assert_eq!(read_family_name(&test_font(&[(1, 1, b"Mac Sans")], 0)).as_deref(), Some("Mac Sans"));
+        let both = test_font(&[(1, 1, b"Mac Sans"), (0, 1, &utf16("Unicode Sans"))], 0);
+        assert_eq!(read_family_name(&both).as_deref(), Some("Unicode Sans"));
+
+        assert_eq!(read_family_name(&test_font(&[(3, 4, &windows)], 0)), None);
+        assert_eq!(read_family_name(&test_font(&[(3, 1, &windows)], 0)[..100]), None);
+        assert_eq!(read_family_name(b"ttcf"), None);
+    }
+
+    #[test]
+    fn family_name_of_collection() {
+        let mut collection = b"ttcf".to_vec();
+        collection.extend([0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 16]);
+        collection.extend(test_font(&[(3, 1, &utf16("First"))], 16));
+        assert_eq!(read_family_name(&collection).as_deref(), Some("First"));
+    }
+
+    #[test]
+    fn layout_kerning_and_lines() {
+        let font = Font::from_bytes(test_font(&[], 0)).unwrap();
+        let style = TextStyle { size: 1000.0, ..TextStyle::default() };
+        let layout = layout_text(&font, "AV\nA V", &style);
+
+        let positions: Vec<_> = layout.glyphs.iter().map(|glyph| (glyph.id, glyph.x, glyph.y)).collect();
+        let expected = [(1, 0.0, 800.0), (2, 500.0, 800.0), (1, 0.0, 1900.0), (3, 600.0, 1900.0), (2, 850.0, 1900.0)];
+        assert_eq!(positions, expected);
+        assert_eq!(layout.line_widths, [1100.0, 1450.0]);
+        assert_eq!((layout.line_height, layout.width, layout.height), (1100.0, 1450.0, 2200.0));
+    }
+
+    #[test]
+    fn layout_alignment_and_spacing() {
+        let font = Font::from_bytes(test_font(&[], 0)).unwrap();
+        let style = TextStyle { size: 1000.0, align: TextAlign::Center, line_spacing: 2.0, ..TextStyle::default() };
+        let layout = layout_text(&font, "AV\nA", &style);
+        let positions: Vec<_> = layout.glyphs.iter().map(|glyph| (glyph.x, glyph.y)).collect();
+        assert_eq!(positions, [(-550.0, 800.0), (-50.0, 800.0), (-300.0, 3000.0)]);
+        assert_eq!(layout.height, 4400.0);
+
+        let style = TextStyle { align: TextAlign::Right, ..style };
+        let layout = layout_text(&font, "AV", &style);
+        assert_eq!(layout.glyphs.iter().map(|glyph| glyph.x).collect::<Vec<_>>(), [-1100.0, -600.0]);
+    }
+}
@@ -0,0 +1,198 @@
+/// 2D affine transform. Maps a point `(x, y)` to:
+///
+/// `x' = a * x + c * y + e`
+///
+/// `y' = b * x + d * y + f`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform { a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: 0.0, f: 0.0 };
+
+    #[inline(always)]
+    pub const fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Self {
+        Self { a, b, c, d, e, f }
+    }
+
+    pub const fn translation(x: f64, y: f64) -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    /// `angle` in radians, clockwise on screen (y axis points down)
+    pub fn rotation(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    pub const fn scaling(sx: f64, sy: f64) -> Self {
+        Self::new(sx, 0.0, 0.0, sy, 0.0, 0.0)
+    }
+
+    /// Maps the unit square `[0, 1] x [0, 1]` onto a `width` x `height` surface
+    pub fn normalized(width: usize, height: usize) -> Self {
+        Self::scaling(width as f64, height as f64)
+    }
+
+    /// Returns transform, that applies `self` first and `next` after it
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            a: next.a * self.a + next.c * self.b,
+            b: next.b * self.a + next.d * self.b,
+            c: next.a * self.c + next.c * self.d,
+            d: next.b * self.c + next.d * self.d,
+            e: next.a * self.e + next.c * self.f + next.e,
+            f: next.b * self.e + next.d * self.f + next.f,
+        }
+    }
+
+    #[inline(always)]
+    pub fn apply(&self, point: (f64, f64)) -> (f64, f64) {
+        (
+            self.a * point.0 + self.c * point.1 + self.e,
+            self.b * point.0 + self.d * point.1 + self.f,
+        )
+    }
+
+    /// Like [`Transform::apply()`], but ignores translation
+    #[inline(always)]
+    pub fn apply_vector(&self, vector: (f64, f64)) -> (f64, f64) {
+        (
+            self.a * vector.0 + self.c * vector.1,
+            self.b * vector.0 + self.d * vector.1,
+        )
+    }
+
+    pub fn determinant(&self) -> f64 {
+        self.a * self.d - self.b * self.c
+    }
+
+    /// Returns `None` if the transform is degenerate (e.g. scale by zero). Tiny scales, as in deep fractal zooms,
+    /// are still invertible
+    pub fn inverse(&self) -> Option<Transform> {
+        let det = self.determinant();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+
+        let a = self.d / det;
+        let b = -self.b / det;
+        let c = -self.c / det;
+        let d = self.a / det;
+        Some(Transform {
+            a,
+            b,
+            c,
+            d,
+            e: -(a * self.e + c * self.f),
+            f: -(b * self.e + d * self.f),
+        })
+    }
+
+    /// Average length scale. Exact for transforms without shear and non-uniform scale
+    pub fn scale_factor(&self) -> f64 {
+        self.determinant().abs().sqrt()
+    }
+
+    /// True if circles stay circles after this transform (rotation, translation and uniform scale only)
+    pub fn is_similarity(&self) -> bool {
+        let eps = 1e-9 * (1.0 + self.a.abs() + self.b.abs());
+        (self.a - self.d).abs() < eps && (self.b + self.c).abs() < eps
+            || (self.a + self.d).abs() < eps && (self.b - self.c).abs() < eps
+    }
+
+    /// True if axis-aligned rectangles stay axis-aligned after this transform
+    pub fn is_axis_aligned(&self) -> bool {
+        self.b == 0.0 && self.c == 0.0
+    }
+}
+
+/// Current transform with stack of saved states.
+///
+/// All operations are applied in local coordinates, like in canvas APIs:
+/// ```
+/// let mut stack = TransformStack::new();
+/// stack.translate(960.0, 540.0);
+/// stack.push();
+/// stack.rotate(std::f64::consts::FRAC_PI_2);
+/// // ... draw rotated around screen centre
+/// stack.pop();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TransformStack {
+    current: Transform,
+    saved: Vec<Transform>,
+}
+
+impl TransformStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_base(base: Transform) -> Self {
+        Self { current: base, saved: Vec::new() }
+    }
+
+    pub fn current(&self) -> &Transform {
+        &self.current
+    }
+
+    pub fn depth(&self) -> usize {
+        self.saved.len()
+    }
+
+    /// Save current transform
+    pub fn push(&mut self) {
+        self.saved.push(self.current);
+    }
+
+    /// Restore last saved transform. Returns false if nothing was saved
+    pub fn pop(&mut self) -> bool {
+        match self.saved.pop() {
+            Some(transform) => {
+                self.current = transform;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replace current transform, keeps saved states
+    pub fn set(&mut self, transform: Transform) {
+        self.current = transform;
+    }
+
+    /// Apply `transform` in local coordinates (before the current one)
+    pub fn transform(&mut self, transform: &Transform) {
+        self.current = transform.then(&self.current);
+    }
+
+    pub fn translate(&mut self, x: f64, y: f64) {
+        self.transform(&Transform::translation(x, y));
+    }
+
+    pub fn rotate(&mut self, angle: f64) {
+        self.transform(&Transform::rotation(angle));
+    }
+
+    pub fn scale(&mut self, sx: f64, sy: f64) {
+        self.transform(&Transform::scaling(sx, sy));
+    }
+
+    #[inline(always)]
+    pub fn apply(&self, point: (f64, f64)) -> (f64, f64) {
+        self.current.apply(point)
+    }
+}